@@ -0,0 +1,85 @@
+use digital_test_runner::{ExpectedEntry, InputEntry, InputValue, Signal, TestCase};
+use std::io::Write;
+
+use crate::Section;
+
+/// An output format for generated test benches.
+///
+/// The [`Builder`](crate::Builder) calls [`header`](TestbenchBackend::header),
+/// [`signal_declarations`](TestbenchBackend::signal_declarations) and
+/// [`begin`](TestbenchBackend::begin) once, then [`stimulus`](TestbenchBackend::stimulus)
+/// followed by [`check`](TestbenchBackend::check) for every row of the test, and finally
+/// [`footer`](TestbenchBackend::footer).
+pub trait TestbenchBackend {
+    /// Called before [`header`](TestbenchBackend::header) with the sections of the test program,
+    /// if sections have been given to the builder
+    fn set_sections(&mut self, _sections: &[Section]) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Write the start of the test bench, up to and including the port list
+    fn header(&mut self, out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()>;
+
+    /// Declare any signals the test bench needs in addition to the ports
+    fn signal_declarations(
+        &mut self,
+        out: &mut dyn Write,
+        signals: &[Signal],
+    ) -> miette::Result<()>;
+
+    /// Write anything needed between the declarations and the first row
+    fn begin(&mut self, _out: &mut dyn Write) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Set the inputs to their initial values at time zero, before the first row.
+    /// Only called if initial inputs or input defaults have been given to the builder.
+    fn initial_inputs(
+        &mut self,
+        _out: &mut dyn Write,
+        _inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Set the inputs of a step of the reset sequence, which is run after the initial inputs
+    /// and before the first row
+    fn reset_step(
+        &mut self,
+        _out: &mut dyn Write,
+        _inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Called before the first row of the section with the given index
+    fn section(
+        &mut self,
+        _out: &mut dyn Write,
+        _index: usize,
+        _section: &Section,
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    /// Apply the inputs of the row on source line `line`. Only inputs whose value changed since
+    /// the previous row are included, except on the first row and on checkpoint rows.
+    fn stimulus(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()>;
+
+    /// Check the outputs of the row on source line `line`. Outputs whose expected value is `X`
+    /// are not included.
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()>;
+
+    /// Write the end of the test bench
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()>;
+}
@@ -0,0 +1,516 @@
+use digital_test_runner::dig;
+use miette::{IntoDiagnostic, WrapErr};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{Builder, Circuit, Hierarchy, Library, TestCaseSelector};
+
+type Configure = Box<dyn for<'b> Fn(Builder<'b>) -> Builder<'b>>;
+
+/// Generates one test bench for each test case in a directory tree of dig files
+pub struct Batch {
+    input_dir: PathBuf,
+    output_dir: PathBuf,
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+    selector: Option<TestCaseSelector>,
+    configure: Configure,
+    makefile: bool,
+    sources: Vec<PathBuf>,
+    library: Option<Library>,
+    input_defaults: bool,
+}
+
+/// A record of what a [`Batch`] generated
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Manifest {
+    pub generated: Vec<GeneratedTest>,
+    pub skipped: Vec<SkippedTest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GeneratedTest {
+    /// The dig file defining the test case, relative to the input directory. Embedded circuits
+    /// found outside the input directory have an absolute path.
+    pub dig: PathBuf,
+    pub index: usize,
+    pub name: String,
+    /// The generated test bench, relative to the output directory
+    pub output: PathBuf,
+    /// The dig file whose Verilog export contains the DUT, relative to the input directory. Digital
+    /// exports embedded circuits together with the circuit embedding them, so for embedded
+    /// circuits this is the dig file the hierarchy was generated for.
+    pub dut: PathBuf,
+    /// The generated top level module connecting the test bench to the DUT, relative to the
+    /// output directory. Only generated together with a Makefile.
+    pub top: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedTest {
+    /// The dig file, relative to the input directory
+    pub dig: PathBuf,
+    /// The index of the skipped test case, or `None` if the whole file was skipped
+    pub index: Option<usize>,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+impl Batch {
+    pub fn new(input_dir: impl Into<PathBuf>, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            input_dir: input_dir.into(),
+            output_dir: output_dir.into(),
+            include: vec![],
+            exclude: vec![],
+            selector: None,
+            configure: Box::new(|builder| builder),
+            makefile: false,
+            sources: vec![],
+            library: None,
+            input_defaults: true,
+        }
+    }
+
+    /// Only process dig files whose path relative to the input directory matches one of the patterns
+    pub fn with_include(mut self, patterns: impl IntoIterator<Item = glob::Pattern>) -> Self {
+        self.include.extend(patterns);
+        self
+    }
+
+    /// Skip dig files whose path relative to the input directory matches one of the patterns
+    pub fn with_exclude(mut self, patterns: impl IntoIterator<Item = glob::Pattern>) -> Self {
+        self.exclude.extend(patterns);
+        self
+    }
+
+    /// Only generate test benches for the test cases matched by `selector`
+    pub fn with_selector(mut self, selector: impl Into<Option<TestCaseSelector>>) -> Self {
+        self.selector = selector.into();
+        self
+    }
+
+    /// Apply `configure` to the [`Builder`] of every test case, eg, to set the delay
+    pub fn with_builder_options(
+        mut self,
+        configure: impl for<'b> Fn(Builder<'b>) -> Builder<'b> + 'static,
+    ) -> Self {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    /// Also write a Makefile to the output directory which compiles each test bench together with
+    /// its DUT using Icarus Verilog. The target `check` runs all test benches and summarizes the
+    /// results.
+    ///
+    /// The DUT of a dig file is expected to be a Verilog file with the same name next to the dig
+    /// file, containing a module with the same name as the file, as exported by Digital. The
+    /// circuits embedded in it are exported to the same Verilog file by Digital.
+    pub fn with_makefile(mut self, makefile: bool) -> Self {
+        self.makefile = makefile;
+        self
+    }
+
+    /// Additional Verilog files that are compiled with every test bench in the Makefile
+    pub fn with_sources(mut self, sources: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.sources.extend(sources);
+        self
+    }
+
+    /// Also generate test benches for the test cases of the circuits embedded in each dig file,
+    /// looking them up in `library`. The test benches of the circuits embedded in `foo.dig` are
+    /// written to the directory `foo`, with a subdirectory for each further level of embedding.
+    /// A dig file is skipped if one of its embedded circuits cannot be found. A dig file of the
+    /// input directory which is embedded in another one is only generated in the directory of
+    /// the circuit embedding it.
+    pub fn with_hierarchy(mut self, library: impl Into<Option<Library>>) -> Self {
+        self.library = library.into();
+        self
+    }
+
+    /// Set the inputs which are not set by a test case to their default values from the dig
+    /// file, as [`Builder::with_input_defaults`] does. This is enabled by default.
+    pub fn with_input_defaults(mut self, enabled: bool) -> Self {
+        self.input_defaults = enabled;
+        self
+    }
+
+    /// Generate the test benches and write `manifest.json` to the output directory
+    pub fn run(&self) -> miette::Result<Manifest> {
+        let mut manifest = Manifest::default();
+
+        std::fs::create_dir_all(&self.output_dir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not create directory {:?}", self.output_dir))?;
+
+        let dig_files = self.find_dig_files()?;
+        if let Some(library) = &self.library {
+            let hierarchies = dig_files
+                .into_iter()
+                .map(|dig| {
+                    let hierarchy = Hierarchy::open(self.input_dir.join(&dig), library);
+                    (dig, hierarchy)
+                })
+                .collect::<Vec<_>>();
+            let embedded = hierarchies
+                .iter()
+                .filter_map(|(_, hierarchy)| hierarchy.as_ref().ok())
+                .flat_map(|hierarchy| hierarchy.circuits().into_iter().skip(1))
+                .filter_map(|(_, circuit)| std::fs::canonicalize(&circuit.path).ok())
+                .collect::<HashSet<_>>();
+            for (dig, hierarchy) in hierarchies {
+                let path = std::fs::canonicalize(self.input_dir.join(&dig));
+                if path.is_ok_and(|path| embedded.contains(&path)) {
+                    continue;
+                }
+                self.process_hierarchy(&dig, hierarchy, &mut manifest)?;
+            }
+        } else {
+            for dig in dig_files {
+                self.process_file(&dig, &dig, &dig, &mut manifest)?;
+            }
+        }
+
+        let manifest_path = self.output_dir.join("manifest.json");
+        let file = std::fs::File::create(&manifest_path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not open file {manifest_path:?} for output"))?;
+        serde_json::to_writer_pretty(file, &manifest)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not write to file {manifest_path:?}"))?;
+
+        if self.makefile {
+            let makefile_path = self.output_dir.join("Makefile");
+            let file = std::fs::File::create(&makefile_path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Could not open file {makefile_path:?} for output"))?;
+            let mut out = std::io::BufWriter::new(file);
+            self.write_makefile(&manifest, &mut out)?;
+            out.flush()
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Could not write to file {makefile_path:?}"))?;
+        }
+
+        Ok(manifest)
+    }
+
+    fn write_makefile(&self, manifest: &Manifest, out: &mut dyn Write) -> miette::Result<()> {
+        let src_dir = std::fs::canonicalize(&self.input_dir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not find directory {:?}", self.input_dir))?;
+        let sources = self
+            .sources
+            .iter()
+            .map(|path| {
+                std::fs::canonicalize(path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Could not find source file {path:?}"))
+                    .map(|path| path.to_string_lossy().into_owned())
+            })
+            .collect::<miette::Result<Vec<_>>>()?;
+        let tests = manifest
+            .generated
+            .iter()
+            .map(|test| {
+                test.output
+                    .with_extension("")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+
+        outputln!(
+            out,
+            "# Generated by digital_test_to_verilog. Run `make check` to simulate all test benches.\n"
+        )?;
+        outputln!(out, "IVERILOG ?= iverilog")?;
+        outputln!(out, "VVP ?= vvp")?;
+        outputln!(out, "IVERILOG_FLAGS ?= -g2012")?;
+        outputln!(out, "SRC_DIR ?= {}", src_dir.to_string_lossy())?;
+        outputln!(out, "SOURCES ?= {}", sources.join(" "))?;
+        outputln!(out)?;
+
+        outputln!(out, "TESTS = \\")?;
+        for test in &tests {
+            outputln!(out, "\t{test} \\")?;
+        }
+        outputln!(out)?;
+
+        outputln!(out, ".PHONY: all check clean\n")?;
+        outputln!(out, "all: $(addsuffix .vvp,$(TESTS))\n")?;
+
+        for (test, generated) in tests.iter().zip(&manifest.generated) {
+            let dut = generated.dut.with_extension("v");
+            let dut = if dut.is_absolute() {
+                dut.to_string_lossy().into_owned()
+            } else {
+                format!("$(SRC_DIR)/{}", dut.to_string_lossy())
+            };
+            let top = generated
+                .top
+                .as_ref()
+                .map(|top| top.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            outputln!(
+                out,
+                "{test}.vvp: {dut} {} {top} $(SOURCES)",
+                generated.output.to_string_lossy()
+            )?;
+            outputln!(out, "\t$(IVERILOG) $(IVERILOG_FLAGS) -s top -o $@ $^\n")?;
+        }
+
+        outputln!(
+            out,
+            r#"check: all
+	@failed=0; \
+	for test in $(TESTS); do \
+		if $(VVP) -n $$test.vvp > $$test.log 2>&1; then \
+			echo "PASS $$test"; \
+		else \
+			echo "FAIL $$test (see $$test.log)"; \
+			failed=$$((failed + 1)); \
+		fi; \
+	done; \
+	echo "$$failed of $(words $(TESTS)) test benches failed"; \
+	test $$failed -eq 0
+
+clean:
+	rm -f $(addsuffix .vvp,$(TESTS)) $(addsuffix .log,$(TESTS))"#
+        )
+    }
+
+    fn find_dig_files(&self) -> miette::Result<Vec<PathBuf>> {
+        let mut result = vec![];
+        let mut dirs = vec![PathBuf::new()];
+
+        while let Some(dir) = dirs.pop() {
+            let full_dir = self.input_dir.join(&dir);
+            let mut entries = std::fs::read_dir(&full_dir)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Could not read directory {full_dir:?}"))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+                .into_diagnostic()?;
+            entries.sort();
+
+            for path in entries {
+                let Some(file_name) = path.file_name() else {
+                    continue;
+                };
+                let relative = dir.join(file_name);
+                if path.is_dir() {
+                    dirs.push(relative);
+                } else if path.extension().is_some_and(|ext| ext == "dig")
+                    && self.is_included(&relative)
+                {
+                    result.push(relative);
+                }
+            }
+        }
+
+        result.sort();
+        Ok(result)
+    }
+
+    fn is_included(&self, relative: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches_path(relative)))
+            && !self.exclude.iter().any(|p| p.matches_path(relative))
+    }
+
+    /// Process the dig file `dig` and the circuits embedded in it
+    fn process_hierarchy(
+        &self,
+        dig: &Path,
+        hierarchy: miette::Result<Hierarchy>,
+        manifest: &mut Manifest,
+    ) -> miette::Result<()> {
+        let hierarchy = match hierarchy {
+            Ok(hierarchy) => hierarchy,
+            Err(err) => {
+                manifest.skipped.push(SkippedTest {
+                    dig: dig.to_path_buf(),
+                    index: None,
+                    name: None,
+                    reason: err.to_string(),
+                });
+                return Ok(());
+            }
+        };
+
+        for (names, circuit) in hierarchy.circuits() {
+            let Some((_, parents)) = names.split_last() else {
+                self.process_file(dig, dig, dig, manifest)?;
+                continue;
+            };
+            let source = match circuit.path.strip_prefix(&self.input_dir) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => std::fs::canonicalize(&circuit.path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Could not find file {:?}", circuit.path))?,
+            };
+            let mut location = dig.with_extension("");
+            location.extend(parents);
+            location.push(format!("{}.dig", circuit.name));
+            self.process_file(&source, &location, dig, manifest)?;
+        }
+        Ok(())
+    }
+
+    /// Process the test cases of the dig file `dig`, writing the test benches as if the dig file
+    /// was at `location` relative to the input directory. The DUT is exported from the dig file
+    /// `dut`, which is `dig` itself unless `dig` is an embedded circuit.
+    fn process_file(
+        &self,
+        dig: &Path,
+        location: &Path,
+        dut: &Path,
+        manifest: &mut Manifest,
+    ) -> miette::Result<()> {
+        let path = self.input_dir.join(dig);
+        let opened = dig::File::open(&path)
+            .map_err(miette::Report::from)
+            .and_then(|dig_file| Ok((dig_file, Circuit::open(&path)?)));
+        let (dig_file, circuit) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                manifest.skipped.push(SkippedTest {
+                    dig: dig.to_path_buf(),
+                    index: None,
+                    name: None,
+                    reason: err.to_string(),
+                });
+                return Ok(());
+            }
+        };
+
+        let out_dir = self
+            .output_dir
+            .join(location.parent().unwrap_or(Path::new("")));
+        std::fs::create_dir_all(&out_dir)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not create directory {out_dir:?}"))?;
+
+        for (index, dig_test_case) in dig_file.test_cases.iter().enumerate() {
+            let name = &dig_test_case.name;
+            if let Some(selector) = &self.selector {
+                if !selector.matches(index, name) {
+                    manifest.skipped.push(SkippedTest {
+                        dig: dig.to_path_buf(),
+                        index: Some(index),
+                        name: Some(name.clone()),
+                        reason: format!("Not matched by the test case selector {selector}"),
+                    });
+                    continue;
+                }
+            }
+
+            let output = output_file_name(location, index, name);
+            let top = self.makefile.then(|| top_file_name(&output));
+            let result = dig_file
+                .load_test(index)
+                .map_err(miette::Report::from)
+                .and_then(|test_case| {
+                    let mut builder = Builder::try_new(&test_case)?;
+                    if self.input_defaults {
+                        builder = builder.with_input_defaults(circuit.inputs());
+                    }
+                    (self.configure)(builder)
+                        .with_output(self.output_dir.join(&output))
+                        .done()?;
+                    if let Some(top) = &top {
+                        let stem = dig
+                            .file_stem()
+                            .map(|s| s.to_string_lossy())
+                            .unwrap_or_default();
+                        let dut_module = if dig == dut {
+                            stem.into_owned()
+                        } else {
+                            embedded_module_name(&stem)
+                        };
+                        self.write_top_module(&test_case, &circuit, &dut_module, top)?;
+                    }
+                    Ok(())
+                });
+
+            match result {
+                Ok(()) => manifest.generated.push(GeneratedTest {
+                    dig: dig.to_path_buf(),
+                    index,
+                    name: name.clone(),
+                    output,
+                    dut: dut.to_path_buf(),
+                    top,
+                }),
+                Err(err) => manifest.skipped.push(SkippedTest {
+                    dig: dig.to_path_buf(),
+                    index: Some(index),
+                    name: Some(name.clone()),
+                    reason: err.to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_top_module(
+        &self,
+        test_case: &digital_test_runner::TestCase,
+        circuit: &Circuit,
+        dut_module: &str,
+        top: &Path,
+    ) -> miette::Result<()> {
+        let path = self.output_dir.join(top);
+        let file = std::fs::File::create(&path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not open file {path:?} for output"))?;
+        let mut out = std::io::BufWriter::new(file);
+        crate::verilog::write_top_module(test_case, &circuit.ports(), dut_module, &mut out)?;
+        out.flush()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not write to file {path:?}"))
+    }
+}
+
+/// The module Digital exports for the embedded circuit `name`, with characters other than
+/// letters, digits and `_` replaced by `_`, eg, `74779_inc` for `74779-inc.dig`
+fn embedded_module_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn top_file_name(output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    output.with_file_name(format!("{stem}_top.v"))
+}
+
+/// The name of the test bench for test case number `index` in the dig file at `dig`.
+///
+/// The test bench is placed in the same relative directory as the dig file and is named
+/// `<file stem>__<index>_<test case name>.v`, with characters other than letters, digits,
+/// `-` and `_` in the name replaced by `_`.
+pub fn output_file_name(dig: &Path, index: usize, name: &str) -> PathBuf {
+    let stem = dig
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let name = if name.is_empty() {
+        String::from("unnamed")
+    } else {
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    dig.with_file_name(format!("{stem}__{index}_{name}.v"))
+}
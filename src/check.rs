@@ -0,0 +1,253 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+use crate::verilog::binary;
+use crate::{TestbenchBackend, Vcd, VcdSignal};
+
+/// A backend which checks the expected outputs of the test against the signals of a VCD file
+/// instead of writing a test bench. The rows are replayed with the same timing as the Verilog
+/// test bench, so a dump of a simulation of the DUT with other stimulus or from another tool
+/// can be checked without running a simulator.
+///
+/// Failed checks are written like the assertion messages of the test bench. The inputs are
+/// checked together with the outputs, so a VCD of a simulation with other stimulus is
+/// reported as well. If any check failed, [`footer`](TestbenchBackend::footer) returns a
+/// [`ChecksFailed`] error.
+#[derive(Debug, Clone)]
+pub struct VcdCheckBackend {
+    vcd: Vcd,
+    scope: Option<String>,
+    delay: (u32, u32),
+    time: u64,
+    /// The signal of the VCD for each output and bidirectional signal of the test
+    signals: Vec<(String, VcdSignal)>,
+    /// The signal of the VCD for each input of the test, with the value set by the test
+    inputs: Vec<(String, VcdSignal, Option<InputValue>)>,
+    checks: usize,
+    failures: usize,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error(
+    "The signal {signal} has {vcd_bits} bits in the VCD file, but {test_bits} bits in the test"
+)]
+pub struct WidthMismatch {
+    pub signal: String,
+    pub vcd_bits: usize,
+    pub test_bits: u64,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("{failures} of {checks} checks failed")]
+pub struct ChecksFailed {
+    pub failures: usize,
+    pub checks: usize,
+}
+
+impl VcdCheckBackend {
+    pub fn new(vcd: Vcd) -> Self {
+        Self {
+            vcd,
+            scope: None,
+            delay: (0, 10),
+            time: 0,
+            signals: vec![],
+            inputs: vec![],
+            checks: 0,
+            failures: 0,
+        }
+    }
+
+    /// Look up the signals of the test in this scope of the VCD, eg, `top.dut`. Without a scope
+    /// the signals are found as described for [`Vcd::find`].
+    pub fn with_scope(mut self, scope: impl Into<Option<String>>) -> Self {
+        self.scope = scope.into();
+        self
+    }
+
+    pub fn with_delay(mut self, delay: (u32, u32)) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// The time of the first row in the VCD, in the time unit of the VCD
+    pub fn with_start_time(mut self, time: u64) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// The value of a signal when it is checked. Without a delay after the check, the next row
+    /// sets its inputs at the same time, so only changes before that time are seen.
+    fn value<'s>(&self, signal: &'s VcdSignal) -> &'s str {
+        let value = if self.delay.1 == 0 {
+            signal.value_before(self.time)
+        } else {
+            signal.value_at(self.time)
+        };
+        value.unwrap_or("x")
+    }
+
+    /// Find the signal of the VCD for a signal of the test
+    fn find(&self, signal: &Signal) -> miette::Result<VcdSignal> {
+        let name = match &self.scope {
+            Some(scope) => format!("{scope}.{}", signal.name),
+            None => signal.name.clone(),
+        };
+        let found = self.vcd.find(&name)?;
+        if found.width as u64 != signal.bits {
+            return Err(WidthMismatch {
+                signal: signal.name.clone(),
+                vcd_bits: found.width,
+                test_bits: signal.bits,
+            }
+            .into());
+        }
+        Ok(found.clone())
+    }
+
+    fn set_inputs<'s>(&mut self, inputs: impl IntoIterator<Item = (&'s Signal, InputValue)>) {
+        for (signal, value) in inputs {
+            if let Some(input) = self
+                .inputs
+                .iter_mut()
+                .find(|(name, _, _)| *name == signal.name)
+            {
+                input.2 = Some(value);
+            }
+        }
+    }
+
+    /// Compare the value of a signal in the VCD with `expected`, and report a mismatch
+    fn compare(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        name: &str,
+        found: &str,
+        expected: &str,
+    ) -> miette::Result<()> {
+        if found != expected {
+            outputln!(
+                out,
+                "ASSERTION FAILED on line {line}: {name} is {found}, expected {expected} at time {}",
+                self.time
+            )?;
+            self.failures += 1;
+        }
+        self.checks += 1;
+        Ok(())
+    }
+}
+
+impl TestbenchBackend for VcdCheckBackend {
+    fn header(&mut self, _out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        self.signals = test_case
+            .signals
+            .iter()
+            .filter(|signal| {
+                matches!(
+                    signal.typ,
+                    SignalType::Output | SignalType::Bidirectional { .. }
+                )
+            })
+            .map(|signal| Ok((signal.name.clone(), self.find(signal)?)))
+            .collect::<miette::Result<_>>()?;
+        self.inputs = test_case
+            .signals
+            .iter()
+            .filter(|signal| matches!(signal.typ, SignalType::Input { .. }))
+            .map(|signal| Ok((signal.name.clone(), self.find(signal)?, None)))
+            .collect::<miette::Result<_>>()?;
+        Ok(())
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied());
+        Ok(())
+    }
+
+    fn reset_step(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied());
+        self.time += u64::from(self.delay.0) + u64::from(self.delay.1);
+        Ok(())
+    }
+
+    fn stimulus(
+        &mut self,
+        _out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().map(|input| (input.signal, input.value)));
+        self.time += u64::from(self.delay.0);
+        Ok(())
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        for i in 0..self.inputs.len() {
+            let (name, signal, value) = &self.inputs[i];
+            let bits = signal.width;
+            let value = match value {
+                Some(InputValue::Value(value)) => binary(*value, bits),
+                Some(InputValue::Z) => "z".repeat(bits),
+                None => continue,
+            };
+            let (name, found) = (name.clone(), self.value(signal).to_string());
+            self.compare(out, line, &name, &found, &value)?;
+        }
+        for output in expected {
+            let Some((_, signal)) = self
+                .signals
+                .iter()
+                .find(|(name, _)| *name == output.signal.name)
+            else {
+                continue;
+            };
+            let bits = output.signal.bits as usize;
+            let value = match output.value {
+                ExpectedValue::Value(value) => binary(value, bits),
+                ExpectedValue::Z => "z".repeat(bits),
+                ExpectedValue::X => continue,
+            };
+            let found = self.value(signal).to_string();
+            self.compare(out, line, &output.signal.name, &found, &value)?;
+        }
+        self.time += u64::from(self.delay.1);
+        Ok(())
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        if self.failures > 0 {
+            return Err(ChecksFailed {
+                failures: self.failures,
+                checks: self.checks,
+            }
+            .into());
+        }
+        outputln!(out, "All {} checks passed.", self.checks)
+    }
+}
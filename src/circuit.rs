@@ -0,0 +1,334 @@
+use digital_test_runner::InputValue;
+use miette::{IntoDiagnostic, WrapErr};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The contents of a dig file, as far as they are not covered by `digital_test_runner::dig`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Circuit {
+    pub attributes: HashMap<String, Attribute>,
+    pub elements: Vec<Element>,
+    pub wires: Vec<Wire>,
+}
+
+/// A visual element of a circuit, eg, a gate, an input or a test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: HashMap<String, Attribute>,
+    pub pos: Point,
+}
+
+/// A straight wire between two points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wire {
+    pub p1: Point,
+    pub p2: Point,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    String(String),
+    Int(i64),
+    Bool(bool),
+    /// A value which may be high impedance, such as the default value of an input
+    Value {
+        value: i64,
+        z: bool,
+    },
+    /// The number of quarter turns counter clockwise
+    Rotation(u32),
+    TestData(String),
+    /// The names of the inputs which are inverted
+    InverterConfig(Vec<String>),
+    /// An attribute of a type which is not supported
+    Other,
+}
+
+/// An `In` element of a circuit, ie, an input port
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputElement {
+    pub name: String,
+    pub bits: u64,
+    /// The value Digital assumes for the input if it is not set
+    pub default: InputValue,
+}
+
+/// A labelled input or output of a circuit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub bits: u64,
+    pub input: bool,
+}
+
+/// The source of a test case in a dig file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestData {
+    pub name: String,
+    pub source: String,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum CircuitError {
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("Expected a <circuit> element")]
+    NotACircuit,
+    #[error("Malformed {0} element")]
+    Malformed(String),
+}
+
+impl Circuit {
+    pub fn open(path: impl AsRef<Path>) -> miette::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not read {path:?}"))?;
+        Self::parse(&text).wrap_err_with(|| format!("Could not parse {path:?}"))
+    }
+
+    pub fn parse(text: &str) -> miette::Result<Self> {
+        Ok(Self::try_parse(text)?)
+    }
+
+    fn try_parse(text: &str) -> Result<Self, CircuitError> {
+        let doc = roxmltree::Document::parse(text)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("circuit") {
+            return Err(CircuitError::NotACircuit);
+        }
+
+        let mut attributes = HashMap::new();
+        let mut elements = vec![];
+        let mut wires = vec![];
+
+        for node in root.children().filter(|node| node.is_element()) {
+            match node.tag_name().name() {
+                "attributes" => attributes = parse_attributes(node)?,
+                "visualElements" => {
+                    for element in node.children().filter(|node| node.is_element()) {
+                        elements.push(parse_element(element)?);
+                    }
+                }
+                "wires" => {
+                    for wire in node.children().filter(|node| node.is_element()) {
+                        wires.push(parse_wire(wire)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            attributes,
+            elements,
+            wires,
+        })
+    }
+
+    /// The test cases of the circuit in the order they appear in the file
+    pub fn test_data(&self) -> Vec<TestData> {
+        self.elements
+            .iter()
+            .filter(|element| element.name == "Testcase")
+            .map(|element| TestData {
+                name: element.label().unwrap_or_default().to_string(),
+                source: match element.attributes.get("Testdata") {
+                    Some(Attribute::TestData(source)) => source.clone(),
+                    _ => String::new(),
+                },
+            })
+            .collect()
+    }
+
+    /// The labelled inputs of the circuit in the order they appear in the file
+    pub fn inputs(&self) -> Vec<InputElement> {
+        self.elements
+            .iter()
+            .filter(|element| element.name == "In")
+            .filter_map(|element| {
+                Some(InputElement {
+                    name: element.label()?.to_string(),
+                    bits: element.bits(),
+                    default: element.input_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// The labelled ports of the circuit in the order of the modules exported by Digital: the
+    /// inputs and clocks followed by the outputs, each in the order they appear in the file
+    pub fn ports(&self) -> Vec<Port> {
+        let port = |element: &Element, input| {
+            Some(Port {
+                name: element.label()?.to_string(),
+                bits: element.bits(),
+                input,
+            })
+        };
+        let inputs = self
+            .elements
+            .iter()
+            .filter(|element| matches!(element.name.as_str(), "In" | "Clock"))
+            .filter_map(|element| port(element, true));
+        let outputs = self
+            .elements
+            .iter()
+            .filter(|element| element.name == "Out")
+            .filter_map(|element| port(element, false));
+        inputs.chain(outputs).collect()
+    }
+}
+
+impl Element {
+    pub fn label(&self) -> Option<&str> {
+        self.string_attribute("Label")
+    }
+
+    /// The `Bits` attribute, which defaults to 1
+    pub fn bits(&self) -> u64 {
+        self.int_attribute("Bits")
+            .and_then(|bits| u64::try_from(bits).ok())
+            .unwrap_or(1)
+    }
+
+    /// The value of an input if it is not set
+    pub fn input_default(&self) -> InputValue {
+        match self.attributes.get("InDefault") {
+            Some(Attribute::Value { z: true, .. }) => InputValue::Z,
+            Some(Attribute::Value { value, z: false }) => InputValue::Value(*value),
+            _ => InputValue::Value(self.int_attribute("Default").unwrap_or(0)),
+        }
+    }
+
+    pub fn string_attribute(&self, key: &str) -> Option<&str> {
+        match self.attributes.get(key) {
+            Some(Attribute::String(s)) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn int_attribute(&self, key: &str) -> Option<i64> {
+        match self.attributes.get(key) {
+            Some(Attribute::Int(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn bool_attribute(&self, key: &str) -> Option<bool> {
+        match self.attributes.get(key) {
+            Some(Attribute::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+fn parse_element(node: roxmltree::Node) -> Result<Element, CircuitError> {
+    let mut name = None;
+    let mut attributes = HashMap::new();
+    let mut pos = None;
+
+    for child in node.children().filter(|node| node.is_element()) {
+        match child.tag_name().name() {
+            "elementName" => name = Some(child.text().unwrap_or_default().to_string()),
+            "elementAttributes" => attributes = parse_attributes(child)?,
+            "pos" => pos = Some(parse_point(child)?),
+            _ => {}
+        }
+    }
+
+    let (Some(name), Some(pos)) = (name, pos) else {
+        return Err(CircuitError::Malformed(String::from("visualElement")));
+    };
+
+    Ok(Element {
+        name,
+        attributes,
+        pos,
+    })
+}
+
+fn parse_wire(node: roxmltree::Node) -> Result<Wire, CircuitError> {
+    let point = |name| {
+        node.children()
+            .find(|child| child.has_tag_name(name))
+            .ok_or_else(|| CircuitError::Malformed(String::from("wire")))
+            .and_then(parse_point)
+    };
+    Ok(Wire {
+        p1: point("p1")?,
+        p2: point("p2")?,
+    })
+}
+
+fn parse_point(node: roxmltree::Node) -> Result<Point, CircuitError> {
+    let coordinate = |name| {
+        node.attribute(name)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| CircuitError::Malformed(node.tag_name().name().to_string()))
+    };
+    Ok(Point {
+        x: coordinate("x")?,
+        y: coordinate("y")?,
+    })
+}
+
+fn parse_attributes(node: roxmltree::Node) -> Result<HashMap<String, Attribute>, CircuitError> {
+    let mut attributes = HashMap::new();
+
+    for entry in node.children().filter(|node| node.is_element()) {
+        let mut children = entry.children().filter(|node| node.is_element());
+        let (Some(key), Some(value)) = (children.next(), children.next()) else {
+            return Err(CircuitError::Malformed(String::from("entry")));
+        };
+        let key = key.text().unwrap_or_default().to_string();
+        attributes.insert(key, parse_attribute_value(value));
+    }
+
+    Ok(attributes)
+}
+
+fn parse_attribute_value(node: roxmltree::Node) -> Attribute {
+    let text = node.text().unwrap_or_default();
+    match node.tag_name().name() {
+        "string" => Attribute::String(text.to_string()),
+        "int" | "long" => text
+            .trim()
+            .parse()
+            .map(Attribute::Int)
+            .unwrap_or(Attribute::Other),
+        "boolean" => Attribute::Bool(text.trim() == "true"),
+        "value" => {
+            let value = node
+                .attribute("v")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            let z = node.attribute("z") == Some("true");
+            Attribute::Value { value, z }
+        }
+        "rotation" => node
+            .attribute("rotation")
+            .and_then(|r| r.parse().ok())
+            .map(Attribute::Rotation)
+            .unwrap_or(Attribute::Other),
+        "testData" => node
+            .children()
+            .find(|node| node.has_tag_name("dataString"))
+            .map(|node| Attribute::TestData(node.text().unwrap_or_default().to_string()))
+            .unwrap_or(Attribute::Other),
+        "inverterConfig" => Attribute::InverterConfig(
+            node.children()
+                .filter(|node| node.has_tag_name("string"))
+                .map(|node| node.text().unwrap_or_default().to_string())
+                .collect(),
+        ),
+        _ => Attribute::Other,
+    }
+}
@@ -0,0 +1,261 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, SignalType, TestCase,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::verilog::mask;
+
+/// How thoroughly a test case exercises the DUT, as printed by the `coverage` command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Coverage {
+    /// The number of rows after loops and clock cycles have been expanded
+    pub rows: usize,
+    pub inputs: Vec<InputCoverage>,
+    pub outputs: Vec<OutputCoverage>,
+}
+
+/// The values driven on an input. Bidirectional signals are listed both as input and as output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InputCoverage {
+    pub name: String,
+    pub bits: u64,
+    /// The distinct values driven on the input, not counting high impedance
+    pub values: BTreeSet<u64>,
+    /// Whether the input was set to high impedance
+    pub high_impedance: bool,
+    /// The transitions seen on each bit, starting with the least significant bit
+    pub toggles: Vec<BitToggles>,
+}
+
+/// The transitions between two driven values seen on a bit of an input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BitToggles {
+    pub rose: bool,
+    pub fell: bool,
+}
+
+/// The states an output was checked in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutputCoverage {
+    pub name: String,
+    pub bits: u64,
+    /// The number of rows in which the output was checked, ie, not expected to be `X`
+    pub checks: usize,
+    /// The distinct values the output was expected to have, not counting high impedance
+    pub values: BTreeSet<u64>,
+    /// Whether the output was expected to be high impedance
+    pub high_impedance: bool,
+}
+
+impl Coverage {
+    /// Run through the rows of a static test case and record the coverage of its signals
+    pub fn try_new(test_case: &TestCase) -> miette::Result<Self> {
+        let mut coverage = Self::empty(test_case);
+        let mut previous = vec![None; coverage.inputs.len()];
+        for row in test_case.try_iter_static()? {
+            let row = row?;
+            coverage.add_row(&mut previous, &row.inputs, &row.expected);
+        }
+        Ok(coverage)
+    }
+
+    fn empty(test_case: &TestCase) -> Self {
+        let inputs = test_case
+            .signals
+            .iter()
+            .filter(|sig| {
+                matches!(
+                    sig.typ,
+                    SignalType::Input { .. } | SignalType::Bidirectional { .. }
+                )
+            })
+            .map(|sig| InputCoverage {
+                name: sig.name.clone(),
+                bits: sig.bits,
+                values: BTreeSet::new(),
+                high_impedance: false,
+                toggles: vec![BitToggles::default(); sig.bits as usize],
+            })
+            .collect();
+        let outputs = test_case
+            .signals
+            .iter()
+            .filter(|sig| {
+                matches!(
+                    sig.typ,
+                    SignalType::Output | SignalType::Bidirectional { .. }
+                )
+            })
+            .map(|sig| OutputCoverage {
+                name: sig.name.clone(),
+                bits: sig.bits,
+                checks: 0,
+                values: BTreeSet::new(),
+                high_impedance: false,
+            })
+            .collect();
+        Self {
+            rows: 0,
+            inputs,
+            outputs,
+        }
+    }
+
+    /// Record a row. `previous` holds the last value driven on each input, which is
+    /// `None` before the first value and after high impedance.
+    fn add_row(
+        &mut self,
+        previous: &mut [Option<u64>],
+        inputs: &[InputEntry<'_>],
+        expected: &[ExpectedEntry<'_>],
+    ) {
+        self.rows += 1;
+        for entry in inputs {
+            let Some(i) = self
+                .inputs
+                .iter()
+                .position(|inp| inp.name == entry.signal.name)
+            else {
+                continue;
+            };
+            let input = &mut self.inputs[i];
+            let value = match entry.value {
+                InputValue::Value(value) => value as u64 & mask(input.bits),
+                InputValue::Z => {
+                    input.high_impedance = true;
+                    previous[i] = None;
+                    continue;
+                }
+            };
+            input.values.insert(value);
+            if let Some(previous) = previous[i] {
+                for (bit, toggles) in input.toggles.iter_mut().enumerate() {
+                    let before = previous >> bit & 1;
+                    let after = value >> bit & 1;
+                    toggles.rose |= before == 0 && after == 1;
+                    toggles.fell |= before == 1 && after == 0;
+                }
+            }
+            previous[i] = Some(value);
+        }
+        for entry in expected {
+            let Some(output) = self
+                .outputs
+                .iter_mut()
+                .find(|out| out.name == entry.signal.name)
+            else {
+                continue;
+            };
+            match entry.value {
+                ExpectedValue::Value(value) => {
+                    output.values.insert(value as u64 & mask(output.bits));
+                }
+                ExpectedValue::Z => output.high_impedance = true,
+                ExpectedValue::X => continue,
+            }
+            output.checks += 1;
+        }
+    }
+
+    /// The outputs which are expected to be `X` in every row
+    pub fn unchecked_outputs(&self) -> impl Iterator<Item = &OutputCoverage> {
+        self.outputs.iter().filter(|out| out.checks == 0)
+    }
+}
+
+impl InputCoverage {
+    /// The bits which both rose and fell during the test
+    pub fn toggled_bits(&self) -> usize {
+        self.toggles
+            .iter()
+            .filter(|toggles| toggles.rose && toggles.fell)
+            .count()
+    }
+}
+
+/// The name of a signal together with its width, as printed by the `list` command
+fn signal_name(name: &str, bits: u64) -> String {
+    if bits > 1 {
+        format!("{name} [{bits}]")
+    } else {
+        name.to_string()
+    }
+}
+
+/// The number of values a signal with `bits` bits can have
+fn possible_values(bits: u64) -> u128 {
+    1u128 << bits.min(64)
+}
+
+impl std::fmt::Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.rows == 1 {
+            writeln!(f, "1 row")?;
+        } else {
+            writeln!(f, "{} rows", self.rows)?;
+        }
+        for input in &self.inputs {
+            writeln!(f, "    {input}")?;
+        }
+        for output in &self.outputs {
+            writeln!(f, "    {output}")?;
+        }
+        let unchecked = self
+            .unchecked_outputs()
+            .map(|out| out.name.as_str())
+            .collect::<Vec<_>>();
+        if !unchecked.is_empty() {
+            writeln!(f, "Never checked: {}", unchecked.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for InputCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "input   {}: {} of {} values",
+            signal_name(&self.name, self.bits),
+            self.values.len(),
+            possible_values(self.bits)
+        )?;
+        if self.high_impedance {
+            write!(f, " and Z")?;
+        }
+        write!(f, ", {} of {} bits toggled", self.toggled_bits(), self.bits)?;
+        let untoggled = self
+            .toggles
+            .iter()
+            .enumerate()
+            .filter(|(_, toggles)| !(toggles.rose && toggles.fell))
+            .map(|(bit, _)| bit.to_string())
+            .collect::<Vec<_>>();
+        if self.bits > 1 && !untoggled.is_empty() && untoggled.len() < self.bits as usize {
+            write!(f, " (not toggled: {})", untoggled.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for OutputCoverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output  {}: ", signal_name(&self.name, self.bits))?;
+        match self.checks {
+            0 => return write!(f, "never checked"),
+            1 => write!(f, "checked in 1 row")?,
+            checks => write!(f, "checked in {checks} rows")?,
+        }
+        write!(
+            f,
+            ", {} of {} values",
+            self.values.len(),
+            possible_values(self.bits)
+        )?;
+        if self.high_impedance {
+            write!(f, " and Z")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+use crate::verilog::{binary, verilog_string, VerilogIdentifier};
+use crate::TestbenchBackend;
+
+/// A backend which writes test benches in the style of the test benches exported by Digital,
+/// eg, `tests/data/74162_tb.v`. The test bench instantiates the DUT itself, stores the rows in a
+/// `patterns` array and stops at the first failed assertion. Initial inputs and reset sequences
+/// can not be expressed in this style and are rejected.
+///
+/// Since the pattern width is only known after all rows have been seen, the whole test bench
+/// is written by [`footer`](TestbenchBackend::footer).
+#[derive(Debug, Clone)]
+pub struct DigitalBackend {
+    module: String,
+    timescale: String,
+    delay: (u32, u32),
+    signals: Vec<PortInfo>,
+    current: Vec<Option<InputValue>>,
+    input_columns: Vec<usize>,
+    output_columns: Vec<usize>,
+    rows: Vec<PatternRow>,
+}
+
+#[derive(Debug, Clone)]
+struct PortInfo {
+    name: String,
+    bits: u64,
+    output: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PatternRow {
+    inputs: Vec<Option<InputValue>>,
+    expected: Vec<(usize, ExpectedValue)>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("Bidirectional signal {0} is not supported by the Digital compatible backend")]
+pub struct BidirectionalNotSupported(String);
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("{0} are not supported by the Digital compatible backend")]
+#[diagnostic(help("The test benches exported by Digital only apply the rows of the test"))]
+pub struct OptionNotSupported(&'static str);
+
+impl DigitalBackend {
+    /// Create a backend for testing the module `module`
+    pub fn new(module: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            timescale: String::from("1us / 1ns"),
+            delay: (10, 0),
+            signals: vec![],
+            current: vec![],
+            input_columns: vec![],
+            output_columns: vec![],
+            rows: vec![],
+        }
+    }
+
+    /// Set the timescale, which defaults to `1us / 1ns` like the test benches exported by Digital
+    pub fn with_timescale(mut self, timescale: impl Into<Option<String>>) -> Self {
+        if let Some(timescale) = timescale.into() {
+            self.timescale = timescale;
+        }
+        self
+    }
+
+    pub fn with_delay(mut self, delay: (u32, u32)) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn index_of(&self, signal: &Signal) -> Option<usize> {
+        self.signals
+            .iter()
+            .position(|port| port.name == signal.name)
+    }
+
+    /// The signals stored in the patterns in column order: inputs in the order they are first
+    /// set, followed by outputs in the order they are first checked
+    fn columns(&self) -> Vec<usize> {
+        let mut columns = self.input_columns.clone();
+        columns.extend(&self.output_columns);
+        columns
+    }
+
+    fn pattern(&self, row: &PatternRow, columns: &[usize]) -> String {
+        columns
+            .iter()
+            .map(|&i| {
+                let bits = self.signals[i].bits as usize;
+                if self.signals[i].output {
+                    match row.expected.iter().find(|(j, _)| *j == i) {
+                        Some((_, ExpectedValue::Value(value))) => binary(*value, bits),
+                        Some((_, ExpectedValue::Z)) => "z".repeat(bits),
+                        Some((_, ExpectedValue::X)) | None => "x".repeat(bits),
+                    }
+                } else {
+                    match row.inputs[i] {
+                        Some(InputValue::Value(value)) => binary(value, bits),
+                        Some(InputValue::Z) => "z".repeat(bits),
+                        None => "x".repeat(bits),
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+/// An identifier followed by a single space, which is needed after escaped identifiers anyway
+fn spaced(identifier: impl std::fmt::Display) -> String {
+    format!("{} ", identifier.to_string().trim_end())
+}
+
+impl TestbenchBackend for DigitalBackend {
+    fn header(&mut self, _out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        self.signals = test_case
+            .signals
+            .iter()
+            .filter_map(|sig| match sig.typ {
+                SignalType::Input { .. } => Some(Ok(PortInfo {
+                    name: sig.name.clone(),
+                    bits: sig.bits,
+                    output: false,
+                })),
+                SignalType::Output => Some(Ok(PortInfo {
+                    name: sig.name.clone(),
+                    bits: sig.bits,
+                    output: true,
+                })),
+                SignalType::Bidirectional { .. } => {
+                    Some(Err(BidirectionalNotSupported(sig.name.clone())))
+                }
+                SignalType::Virtual { .. } => None,
+            })
+            .collect::<Result<_, _>>()?;
+        self.current = vec![None; self.signals.len()];
+        Ok(())
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        _out: &mut dyn Write,
+        _inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        Err(OptionNotSupported("Initial inputs and input defaults").into())
+    }
+
+    fn reset_step(
+        &mut self,
+        _out: &mut dyn Write,
+        _inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        Err(OptionNotSupported("Reset sequences").into())
+    }
+
+    fn stimulus(
+        &mut self,
+        _out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        for input in inputs {
+            if let Some(i) = self.index_of(input.signal) {
+                self.current[i] = Some(input.value);
+                if !self.input_columns.contains(&i) {
+                    self.input_columns.push(i);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check(
+        &mut self,
+        _out: &mut dyn Write,
+        _line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        let expected = expected
+            .iter()
+            .filter_map(|output| Some((self.index_of(output.signal)?, output.value)))
+            .collect::<Vec<_>>();
+        for (i, _) in &expected {
+            if !self.output_columns.contains(i) {
+                self.output_columns.push(*i);
+            }
+        }
+        self.rows.push(PatternRow {
+            inputs: self.current.clone(),
+            expected,
+        });
+        Ok(())
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        let tb_module = format!("{}_tb", self.module);
+        outputln!(out, "//  A testbench for {tb_module}")?;
+        outputln!(out, "`timescale {}\n", self.timescale)?;
+        outputln!(out, "module {};", VerilogIdentifier::from(&tb_module))?;
+
+        for port in &self.signals {
+            let identifier = VerilogIdentifier::from(&port.name);
+            let range = if port.bits > 1 {
+                format!("[{}:0] ", port.bits - 1)
+            } else {
+                String::new()
+            };
+            if port.output {
+                outputln!(out, "  wire {range}{identifier};")?;
+            } else if range.is_empty() {
+                outputln!(out, "  reg  {identifier};")?;
+            } else {
+                outputln!(out, "  reg {range}{identifier};")?;
+            }
+        }
+        outputln!(out)?;
+
+        let connections = self
+            .signals
+            .iter()
+            .map(|port| {
+                let identifier = VerilogIdentifier::from(&port.name);
+                format!("      .{identifier}({identifier})")
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        outputln!(
+            out,
+            "  {}{}(\n{connections}\n  );\n",
+            spaced(VerilogIdentifier::from(&self.module)),
+            spaced(VerilogIdentifier::from(&format!("{}0", self.module)))
+        )?;
+
+        let columns = self.columns();
+        let width: u64 = columns.iter().map(|&i| self.signals[i].bits).sum();
+        let count = self.rows.len();
+        outputln!(
+            out,
+            "  reg [{}:0] patterns[0:{}];",
+            width.saturating_sub(1),
+            count.saturating_sub(1)
+        )?;
+        outputln!(out, "  integer i;\n")?;
+        outputln!(out, "  initial begin")?;
+        for (n, row) in self.rows.iter().enumerate() {
+            outputln!(
+                out,
+                "    patterns[{n}] = {width}'b{};",
+                self.pattern(row, &columns)
+            )?;
+        }
+        outputln!(out)?;
+
+        let mut lsb = width;
+        let selects = columns
+            .iter()
+            .map(|&i| {
+                let port = &self.signals[i];
+                lsb -= port.bits;
+                let select = if port.bits > 1 {
+                    format!("[{}:{lsb}]", lsb + port.bits - 1)
+                } else {
+                    format!("[{lsb}]")
+                };
+                (port, select)
+            })
+            .collect::<Vec<_>>();
+
+        outputln!(out, "    for (i = 0; i < {count}; i = i + 1) begin")?;
+        for (port, select) in selects.iter().filter(|(port, _)| !port.output) {
+            let identifier = VerilogIdentifier::from(&port.name);
+            outputln!(out, "      {}= patterns[i]{select};", spaced(&identifier))?;
+        }
+        outputln!(out, "      #{};", self.delay.0)?;
+        for (port, select) in selects.iter().filter(|(port, _)| port.output) {
+            let identifier = VerilogIdentifier::from(&port.name);
+            let message = verilog_string(&format!(
+                "%d:{}: (assertion error). Expected %h, found %h",
+                port.name.replace('%', "%%")
+            ));
+            outputln!(
+                out,
+                "      if (patterns[i]{select} !== {}'hx) begin",
+                port.bits
+            )?;
+            outputln!(
+                out,
+                "        if ({}!== patterns[i]{select}) begin",
+                spaced(&identifier)
+            )?;
+            outputln!(
+                out,
+                "          $display({message}, i, patterns[i]{select}, {identifier});"
+            )?;
+            outputln!(out, "          $finish;")?;
+            outputln!(out, "        end")?;
+            outputln!(out, "      end")?;
+        }
+        if self.delay.1 > 0 {
+            outputln!(out, "      #{};", self.delay.1)?;
+        }
+        outputln!(out, "    end\n")?;
+        outputln!(out, "    $display(\"All tests passed.\");")?;
+        outputln!(out, "  end")?;
+        outputln!(out, "endmodule")
+    }
+}
@@ -0,0 +1,257 @@
+use miette::IntoDiagnostic;
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::circuit::Circuit;
+use crate::netlist::{Component, Gate, Kind, Netlist};
+use crate::verilog::VerilogIdentifier;
+use crate::Library;
+
+/// Write a Verilog module `module` which implements `circuit`, as a replacement for exporting
+/// the DUT from Digital. Like in Digital's export the ports are the inputs followed by the
+/// outputs, each in the order of the elements in the circuit. Every embedded circuit becomes
+/// a module of its own, which is written before the modules using it.
+pub fn write_netlist(
+    circuit: &Circuit,
+    module: &str,
+    library: &Library,
+    out: &mut dyn Write,
+) -> miette::Result<()> {
+    let netlist = Netlist::new(circuit, library)?;
+    let mut written = HashSet::new();
+    write_subcircuits(&netlist, &mut written, out)?;
+    write_module(&netlist, module, out)
+}
+
+fn write_subcircuits(
+    netlist: &Netlist,
+    written: &mut HashSet<String>,
+    out: &mut dyn Write,
+) -> miette::Result<()> {
+    for component in &netlist.components {
+        if let Kind::Subcircuit(subcircuit) = &component.kind {
+            if written.insert(subcircuit.name.clone()) {
+                write_subcircuits(&subcircuit.netlist, written, out)?;
+                write_module(&subcircuit.netlist, &subcircuit.name, out)?;
+                outputln!(out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn range(bits: u64) -> String {
+    if bits > 1 {
+        format!("[{}:0] ", bits - 1)
+    } else {
+        String::new()
+    }
+}
+
+/// The name of a port, which is the label of the input or output
+fn port_name(component: &Component, index: usize) -> String {
+    component
+        .label
+        .clone()
+        .unwrap_or_else(|| format!("p{index}"))
+}
+
+/// The inputs or outputs of a netlist with their names
+fn ports(netlist: &Netlist, inputs: bool) -> impl Iterator<Item = (String, &Component)> {
+    netlist
+        .components
+        .iter()
+        .enumerate()
+        .filter(move |(_, component)| match component.kind {
+            Kind::Input(_) => inputs,
+            Kind::Output => !inputs,
+            _ => false,
+        })
+        .map(|(i, component)| (port_name(component, i), component))
+}
+
+fn net(index: usize) -> String {
+    format!("s{index}")
+}
+
+/// Select the bits `lsb` to `msb` of a net of width `bits`
+fn select(index: usize, bits: u64, msb: u64, lsb: u64) -> String {
+    if bits == 1 {
+        net(index)
+    } else if msb == lsb {
+        format!("{}[{msb}]", net(index))
+    } else {
+        format!("{}[{msb}:{lsb}]", net(index))
+    }
+}
+
+fn write_module(netlist: &Netlist, module: &str, out: &mut dyn Write) -> miette::Result<()> {
+    let declarations = ports(netlist, true)
+        .map(|port| ("input", port))
+        .chain(ports(netlist, false).map(|port| ("output", port)))
+        .map(|(direction, (name, port))| {
+            format!(
+                "  {direction} {}{}",
+                range(port.bits),
+                VerilogIdentifier::from(&name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    outputln!(
+        out,
+        "module {} (\n{declarations}\n);",
+        VerilogIdentifier::from(module)
+    )?;
+    for (i, bits) in netlist.nets.iter().enumerate() {
+        outputln!(out, "  wire {}{};", range(*bits), net(i))?;
+    }
+
+    for (index, component) in netlist.components.iter().enumerate() {
+        let input = |i: usize| net(component.inputs[i]);
+        let output = |i: usize| net(component.outputs[i]);
+        match &component.kind {
+            Kind::Input(_) => {
+                let name = port_name(component, index);
+                outputln!(
+                    out,
+                    "  assign {} = {};",
+                    output(0),
+                    VerilogIdentifier::from(&name)
+                )?;
+            }
+            Kind::Output => {
+                let name = port_name(component, index);
+                outputln!(
+                    out,
+                    "  assign {} = {};",
+                    VerilogIdentifier::from(&name),
+                    input(0)
+                )?;
+            }
+            Kind::Const(value) => {
+                outputln!(out, "  assign {} = {}'d{value};", output(0), component.bits)?;
+            }
+            Kind::Gate { gate, invert } => {
+                let operator = match gate {
+                    Gate::And => " & ",
+                    Gate::Or => " | ",
+                    Gate::XOr => " ^ ",
+                };
+                let expression = (0..component.inputs.len())
+                    .map(input)
+                    .collect::<Vec<_>>()
+                    .join(operator);
+                if *invert {
+                    outputln!(out, "  assign {} = ~({expression});", output(0))?;
+                } else {
+                    outputln!(out, "  assign {} = {expression};", output(0))?;
+                }
+            }
+            Kind::Not => outputln!(out, "  assign {} = ~{};", output(0), input(0))?,
+            Kind::Multiplexer => {
+                let count = component.inputs.len() - 1;
+                let mut expression = input(count);
+                for i in (0..count - 1).rev() {
+                    expression = format!("{} == {i} ? {} : {expression}", input(0), input(i + 1));
+                }
+                outputln!(out, "  assign {} = {expression};", output(0))?;
+            }
+            Kind::Splitter { inputs, outputs } => {
+                let mut lsb = 0;
+                let parts = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, bits)| {
+                        let part = (lsb, lsb + bits - 1, i);
+                        lsb += bits;
+                        part
+                    })
+                    .collect::<Vec<_>>();
+                let mut lsb = 0;
+                for (o, bits) in outputs.iter().enumerate() {
+                    let msb = lsb + bits - 1;
+                    let selects = parts
+                        .iter()
+                        .rev()
+                        .filter(|(low, high, _)| *low <= msb && lsb <= *high)
+                        .map(|&(low, high, i)| {
+                            let net = component.inputs[i];
+                            select(
+                                net,
+                                netlist.nets[net],
+                                msb.min(high) - low,
+                                lsb.max(low) - low,
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    let expression = match &selects[..] {
+                        [select] => select.clone(),
+                        _ => format!("{{{}}}", selects.join(", ")),
+                    };
+                    outputln!(out, "  assign {} = {expression};", output(o))?;
+                    lsb += bits;
+                }
+            }
+            Kind::Driver => outputln!(
+                out,
+                "  assign {} = {} ? {} : {}'bz;",
+                output(0),
+                input(1),
+                input(0),
+                component.bits
+            )?,
+            Kind::DFlipFlop => {
+                let register = format!("r{index}");
+                outputln!(out, "  reg {}{register} = 0;", range(component.bits))?;
+                outputln!(
+                    out,
+                    "  always @(posedge {}) {register} <= {};",
+                    input(1),
+                    input(0)
+                )?;
+                outputln!(out, "  assign {} = {register};", output(0))?;
+                outputln!(out, "  assign {} = ~{register};", output(1))?;
+            }
+            Kind::Add => outputln!(
+                out,
+                "  assign {{{}, {}}} = {} + {} + {};",
+                output(1),
+                output(0),
+                input(0),
+                input(1),
+                input(2)
+            )?,
+            Kind::Comparator { signed } => {
+                let (a, b) = if *signed {
+                    (
+                        format!("$signed({})", input(0)),
+                        format!("$signed({})", input(1)),
+                    )
+                } else {
+                    (input(0), input(1))
+                };
+                for (i, operator) in [">", "==", "<"].iter().enumerate() {
+                    outputln!(out, "  assign {} = {a} {operator} {b};", output(i))?;
+                }
+            }
+            Kind::Subcircuit(subcircuit) => {
+                let netlist = &subcircuit.netlist;
+                let connections = ports(netlist, true)
+                    .zip(&component.inputs)
+                    .chain(ports(netlist, false).zip(&component.outputs))
+                    .map(|((name, _), &pin)| {
+                        format!("    .{}({})", VerilogIdentifier::from(&name), net(pin))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+                outputln!(
+                    out,
+                    "  {} i{index} (\n{connections}\n  );",
+                    VerilogIdentifier::from(&subcircuit.name)
+                )?;
+            }
+        }
+    }
+    outputln!(out, "endmodule")
+}
@@ -0,0 +1,69 @@
+use std::ops::RangeInclusive;
+
+use crate::Section;
+
+/// Restricts which rows of a test are included in the test bench
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowFilter {
+    lines: Vec<RangeInclusive<usize>>,
+    rows: Option<RangeInclusive<usize>>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("No section \"{name}\" found")]
+pub struct SectionNotFound {
+    name: String,
+    #[help]
+    available: String,
+}
+
+impl RowFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only include rows from the (one based) source lines in `lines`
+    pub fn with_lines(mut self, lines: RangeInclusive<usize>) -> Self {
+        self.lines.push(lines);
+        self
+    }
+
+    /// Only include rows with a (zero based) index in `rows`, counting rows after loops and clock
+    /// cycles have been expanded
+    pub fn with_rows(mut self, rows: RangeInclusive<usize>) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    /// Only include rows from the section called `name`
+    pub fn with_section(self, sections: &[Section], name: &str) -> Result<Self, SectionNotFound> {
+        let Some(i) = sections.iter().position(|section| section.name == name) else {
+            let available = sections
+                .iter()
+                .map(|section| section.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(SectionNotFound {
+                name: name.to_string(),
+                available: format!("The available sections are: {available}"),
+            });
+        };
+        let first = sections[i].line + 1;
+        let last = sections
+            .get(i + 1)
+            .map(|section| section.line - 1)
+            .unwrap_or(usize::MAX);
+        Ok(self.with_lines(first..=last))
+    }
+
+    /// Check if the row with index `index` from source line `line` should be included
+    pub fn matches(&self, index: usize, line: usize) -> bool {
+        self.lines.iter().all(|lines| lines.contains(&line))
+            && self.rows.as_ref().is_none_or(|rows| rows.contains(&index))
+    }
+
+    /// Check if no row after the row with index `index` can match
+    pub fn is_done(&self, index: usize) -> bool {
+        self.rows.as_ref().is_some_and(|rows| index >= *rows.end())
+    }
+}
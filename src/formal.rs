@@ -0,0 +1,432 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::{IntoDiagnostic, WrapErr};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::verilog::{binary, VerilogIdentifier};
+use crate::{Port, TestbenchBackend};
+
+/// How the rows of the test are checked by the formal test bench
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FormalMode {
+    /// Apply one row in each step of the bounded model check, which also works for sequential
+    /// circuits
+    #[default]
+    Sequential,
+    /// Check all rows in a single step, using one instance of the DUT for each row. This only
+    /// works for combinational circuits, whose test rows are a truth table.
+    SingleStep,
+}
+
+/// A backend which writes a formal test bench for SymbiYosys. Inputs are free signals, which
+/// are constrained to the values of each row with `assume`, and the expected outputs are
+/// checked with `assert`. Inputs which are `Z` or not set by the test are left unconstrained.
+///
+/// Like [`DigitalBackend`](crate::DigitalBackend), the test bench instantiates the DUT itself
+/// and is written by [`footer`](TestbenchBackend::footer), together with the `.sby` file if one
+/// was requested. The DUT is connected by name, or by position if its ports are given with
+/// [`with_ports`](Self::with_ports).
+#[derive(Debug, Clone)]
+pub struct FormalBackend {
+    module: String,
+    mode: FormalMode,
+    sby: Option<SbyFile>,
+    ports: Vec<Port>,
+    signals: Vec<PortInfo>,
+    current: Vec<Option<InputValue>>,
+    rows: Vec<FormalRow>,
+}
+
+/// The `.sby` file written by a [`FormalBackend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbyFile {
+    pub path: PathBuf,
+    /// The Verilog files to read, which are the DUT and the formal test bench
+    pub sources: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+struct PortInfo {
+    name: String,
+    bits: u64,
+    output: bool,
+}
+
+#[derive(Debug, Clone)]
+struct FormalRow {
+    /// The source line, or `None` for a step of the reset sequence
+    line: Option<usize>,
+    inputs: Vec<Option<InputValue>>,
+    expected: Vec<(usize, i64)>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum FormalError {
+    #[error("Bidirectional signal {0} is not supported by the formal backend")]
+    BidirectionalNotSupported(String),
+    #[error("The expected value Z of {signal} on line {line} can not be checked formally")]
+    #[diagnostic(help("Formal tools only model the values 0 and 1"))]
+    HighImpedanceNotSupported { signal: String, line: usize },
+}
+
+impl FormalBackend {
+    /// Create a backend for testing the module `module`
+    pub fn new(module: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            mode: FormalMode::default(),
+            sby: None,
+            ports: vec![],
+            signals: vec![],
+            current: vec![],
+            rows: vec![],
+        }
+    }
+
+    pub fn with_mode(mut self, mode: FormalMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Also write a `.sby` file which runs a bounded model check of the test bench with
+    /// yosys-smtbmc. The depth is the number of rows, or one in [`FormalMode::SingleStep`].
+    pub fn with_sby(mut self, sby: impl Into<Option<SbyFile>>) -> Self {
+        self.sby = sby.into();
+        self
+    }
+
+    /// Connect the DUT by position to these ports, such as [`Circuit::ports`](crate::Circuit::ports)
+    /// for a module exported by Digital, which renames ports whose label is not a valid
+    /// identifier. Ports which are not part of the test are left unconnected.
+    pub fn with_ports(mut self, ports: Vec<Port>) -> Self {
+        self.ports = ports;
+        self
+    }
+
+    /// The name of the test bench module
+    fn top_module(&self) -> String {
+        format!("{}_formal", self.module)
+    }
+
+    fn index_of(&self, signal: &Signal) -> Option<usize> {
+        self.signals
+            .iter()
+            .position(|port| port.name == signal.name)
+    }
+
+    fn set_inputs<'s>(&mut self, inputs: impl IntoIterator<Item = (&'s Signal, InputValue)>) {
+        for (signal, value) in inputs {
+            if let Some(i) = self.index_of(signal) {
+                self.current[i] = Some(value);
+            }
+        }
+    }
+
+    fn write_sequential(&self, out: &mut dyn Write) -> miette::Result<()> {
+        for port in &self.signals {
+            let declaration = format!("{}{}", range(port.bits), identifier(&port.name));
+            if port.output {
+                outputln!(out, "  wire {declaration};")?;
+            } else {
+                outputln!(out, "  (* anyseq *) wire {declaration};")?;
+            }
+        }
+        outputln!(out)?;
+
+        let connections = self
+            .signals
+            .iter()
+            .map(|port| (port, identifier(&port.name)))
+            .collect::<Vec<_>>();
+        self.write_instance(out, "dut", &connections)?;
+
+        let last = self.rows.len().saturating_sub(1);
+        let bits = (usize::BITS - last.leading_zeros()).max(1);
+        outputln!(out, "  reg {}step = 0;\n", range(u64::from(bits)))?;
+        outputln!(out, "  always @($global_clock) begin")?;
+        outputln!(out, "    if (step < {last}) step <= step + 1;")?;
+        outputln!(out, "  end\n")?;
+
+        outputln!(out, "  always @* begin")?;
+        outputln!(out, "    case (step)")?;
+        for (n, row) in self.rows.iter().enumerate() {
+            match row.line {
+                Some(line) => outputln!(out, "      {n}: begin // line {line}")?,
+                None => outputln!(out, "      {n}: begin // reset")?,
+            }
+            for (port, value) in self.signals.iter().zip(&row.inputs) {
+                if let Some(InputValue::Value(value)) = value {
+                    outputln!(
+                        out,
+                        "        assume({} == {});",
+                        identifier(&port.name),
+                        constant(*value, port.bits)
+                    )?;
+                }
+            }
+            for &(i, value) in &row.expected {
+                let port = &self.signals[i];
+                outputln!(
+                    out,
+                    "        assert({} == {});",
+                    identifier(&port.name),
+                    constant(value, port.bits)
+                )?;
+            }
+            outputln!(out, "      end")?;
+        }
+        outputln!(out, "    endcase")?;
+        outputln!(out, "  end")
+    }
+
+    fn write_single_step(&self, out: &mut dyn Write) -> miette::Result<()> {
+        let mut first = true;
+        for (n, row) in self.rows.iter().enumerate() {
+            let Some(line) = row.line.filter(|_| !row.expected.is_empty()) else {
+                continue;
+            };
+            if !first {
+                outputln!(out)?;
+            }
+            first = false;
+            outputln!(out, "  // line {line}")?;
+            let wire = |i: usize| format!("row{n}_{i}");
+            let mut connections = vec![];
+            for (i, (port, value)) in self.signals.iter().zip(&row.inputs).enumerate() {
+                let connection = match value {
+                    Some(InputValue::Value(value)) if !port.output => constant(*value, port.bits),
+                    _ => {
+                        let attribute = if port.output { "" } else { "(* anyconst *) " };
+                        outputln!(out, "  {attribute}wire {}{};", range(port.bits), wire(i))?;
+                        wire(i)
+                    }
+                };
+                connections.push((port, connection));
+            }
+            self.write_instance(out, &format!("row{n}"), &connections)?;
+            outputln!(out, "  always @* begin")?;
+            for &(i, value) in &row.expected {
+                let port = &self.signals[i];
+                outputln!(
+                    out,
+                    "    assert({} == {});",
+                    wire(i),
+                    constant(value, port.bits)
+                )?;
+            }
+            outputln!(out, "  end")?;
+        }
+        Ok(())
+    }
+
+    fn write_instance(
+        &self,
+        out: &mut dyn Write,
+        name: &str,
+        connections: &[(&PortInfo, String)],
+    ) -> miette::Result<()> {
+        let connections = if self.ports.is_empty() {
+            connections
+                .iter()
+                .map(|(port, connection)| {
+                    format!("      .{}({connection})", identifier(&port.name))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            self.ports
+                .iter()
+                .map(
+                    |port| match connections.iter().find(|(info, _)| info.name == port.name) {
+                        Some((_, connection)) => format!("      {connection}"),
+                        None => String::from("      /* unconnected */"),
+                    },
+                )
+                .collect()
+        }
+        .join(",\n");
+        outputln!(
+            out,
+            "  {} {name} (\n{connections}\n  );\n",
+            identifier(&self.module)
+        )
+    }
+
+    fn write_sby(&self, sby: &SbyFile) -> miette::Result<()> {
+        let path = &sby.path;
+        let file = std::fs::File::create(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not open file {path:?} for output"))?;
+        let mut out = std::io::BufWriter::new(file);
+        let depth = match self.mode {
+            FormalMode::Sequential => self.rows.len().max(1),
+            FormalMode::SingleStep => 1,
+        };
+        let names = sby
+            .sources
+            .iter()
+            .map(|source| {
+                source
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+
+        outputln!(out, "[options]")?;
+        outputln!(out, "mode bmc")?;
+        outputln!(out, "depth {depth}")?;
+        if self.mode == FormalMode::Sequential {
+            outputln!(out, "multiclock on")?;
+        }
+        outputln!(out, "\n[engines]")?;
+        outputln!(out, "smtbmc")?;
+        outputln!(out, "\n[script]")?;
+        outputln!(out, "read -formal {}", names.join(" "))?;
+        outputln!(out, "prep -top {}", self.top_module())?;
+        outputln!(out, "\n[files]")?;
+        for source in &sby.sources {
+            outputln!(out, "{}", source.to_string_lossy())?;
+        }
+        out.flush()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not write to file {path:?}"))
+    }
+}
+
+fn identifier(name: &str) -> String {
+    VerilogIdentifier::from(name).to_string()
+}
+
+fn range(bits: u64) -> String {
+    if bits > 1 {
+        format!("[{}:0] ", bits - 1)
+    } else {
+        String::new()
+    }
+}
+
+fn constant(value: i64, bits: u64) -> String {
+    format!("{bits}'b{}", binary(value, bits as usize))
+}
+
+impl TestbenchBackend for FormalBackend {
+    fn header(&mut self, _out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        self.signals = test_case
+            .signals
+            .iter()
+            .filter_map(|sig| match sig.typ {
+                SignalType::Input { .. } => Some(Ok(PortInfo {
+                    name: sig.name.clone(),
+                    bits: sig.bits,
+                    output: false,
+                })),
+                SignalType::Output => Some(Ok(PortInfo {
+                    name: sig.name.clone(),
+                    bits: sig.bits,
+                    output: true,
+                })),
+                SignalType::Bidirectional { .. } => Some(Err(
+                    FormalError::BidirectionalNotSupported(sig.name.clone()),
+                )),
+                SignalType::Virtual { .. } => None,
+            })
+            .collect::<Result<_, _>>()?;
+        self.current = vec![None; self.signals.len()];
+        Ok(())
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied());
+        Ok(())
+    }
+
+    fn reset_step(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied());
+        self.rows.push(FormalRow {
+            line: None,
+            inputs: self.current.clone(),
+            expected: vec![],
+        });
+        Ok(())
+    }
+
+    fn stimulus(
+        &mut self,
+        _out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().map(|input| (input.signal, input.value)));
+        Ok(())
+    }
+
+    fn check(
+        &mut self,
+        _out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        let mut checks = vec![];
+        for output in expected {
+            let Some(i) = self.index_of(output.signal) else {
+                continue;
+            };
+            match output.value {
+                ExpectedValue::Value(value) => checks.push((i, value)),
+                ExpectedValue::Z => {
+                    return Err(FormalError::HighImpedanceNotSupported {
+                        signal: output.signal.name.clone(),
+                        line,
+                    }
+                    .into())
+                }
+                ExpectedValue::X => {}
+            }
+        }
+        self.rows.push(FormalRow {
+            line: Some(line),
+            inputs: self.current.clone(),
+            expected: checks,
+        });
+        Ok(())
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(
+            out,
+            "// A formal test bench for {}, to be checked with SymbiYosys\n",
+            self.module
+        )?;
+        outputln!(out, "module {};", identifier(&self.top_module()))?;
+        match self.mode {
+            FormalMode::Sequential => self.write_sequential(out)?,
+            FormalMode::SingleStep => self.write_single_step(out)?,
+        }
+        outputln!(out, "endmodule")?;
+
+        if let Some(sby) = &self.sby {
+            self.write_sby(sby)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+use crate::netlist::Resolver;
+use crate::{Circuit, Library};
+
+/// A circuit together with the circuits embedded in it, such as `74779-inc.dig` in `74779.dig`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hierarchy {
+    /// The name of the dig file without extension
+    pub name: String,
+    pub path: PathBuf,
+    /// The distinct circuits embedded in this circuit, in the order of their first instance
+    pub children: Vec<Hierarchy>,
+}
+
+impl Hierarchy {
+    /// Load the dig file `path` and the circuits embedded in it, recursively. Fails if an
+    /// embedded circuit cannot be found in `library`.
+    pub fn open(path: impl AsRef<Path>, library: &Library) -> miette::Result<Self> {
+        Self::load(path.as_ref(), &mut Resolver::new(library))
+    }
+
+    fn load(path: &Path, resolver: &mut Resolver) -> miette::Result<Self> {
+        let circuit = Circuit::open(path)?;
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+
+        resolver.enter(path);
+        let mut children: Vec<Hierarchy> = vec![];
+        for element in &circuit.elements {
+            if !element.name.ends_with(".dig") {
+                continue;
+            }
+            let child_path = resolver.resolve(&element.name)?;
+            if children.iter().any(|known| known.path == child_path) {
+                continue;
+            }
+            children.push(Self::load(&child_path, resolver)?);
+        }
+        resolver.leave();
+
+        Ok(Self {
+            name,
+            path: path.to_path_buf(),
+            children,
+        })
+    }
+
+    /// All circuits of the hierarchy depth first, starting with this circuit, together with the
+    /// names of the embedded circuits leading to them. A circuit embedded in several places is
+    /// only listed at its first place.
+    pub fn circuits(&self) -> Vec<(Vec<&str>, &Hierarchy)> {
+        let mut result = vec![];
+        self.collect(vec![], &mut result);
+        result
+    }
+
+    fn collect<'a>(&'a self, names: Vec<&'a str>, result: &mut Vec<(Vec<&'a str>, &'a Self)>) {
+        if result.iter().any(|(_, circuit)| circuit.path == self.path) {
+            return;
+        }
+        result.push((names.clone(), self));
+        for child in &self.children {
+            let mut names = names.clone();
+            names.push(&child.name);
+            child.collect(names, result);
+        }
+    }
+}
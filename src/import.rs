@@ -0,0 +1,240 @@
+use miette::{IntoDiagnostic, WrapErr};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A test reconstructed from a test bench exported by Digital, eg, `tests/data/74162_tb.v`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedTest {
+    /// The signals in the order of the columns of the test data
+    pub signals: Vec<ImportedSignal>,
+    pub rows: Vec<Vec<ImportedValue>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedSignal {
+    pub name: String,
+    /// The most significant bit of the signal in the patterns
+    pub msb: usize,
+    /// The least significant bit of the signal in the patterns
+    pub lsb: usize,
+    pub output: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportedValue {
+    Value(i64),
+    X,
+    Z,
+    /// A clock pulse, written as `C` in the test data
+    Clock,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ImportError {
+    #[error("No patterns found")]
+    #[diagnostic(help("Expected lines such as \"patterns[0] = 4'b0_1_x_x;\""))]
+    NoPatterns,
+    #[error("No signals found")]
+    #[diagnostic(help(
+        "Expected lines such as \"A = patterns[i][3];\" in the loop over the patterns"
+    ))]
+    NoSignals,
+    #[error("Pattern {index} has {found} bits, expected {expected}")]
+    WrongWidth {
+        index: usize,
+        found: usize,
+        expected: usize,
+    },
+    #[error("Pattern {index} has a mix of x or z with other bits in signal {signal}")]
+    MixedBits { index: usize, signal: String },
+    #[error("There is no input called {0}")]
+    UnknownClock(String),
+}
+
+impl std::fmt::Display for ImportedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportedValue::Value(value) => write!(f, "{value}"),
+            ImportedValue::X => write!(f, "X"),
+            ImportedValue::Z => write!(f, "Z"),
+            ImportedValue::Clock => write!(f, "C"),
+        }
+    }
+}
+
+impl ImportedTest {
+    pub fn open(path: impl AsRef<Path>) -> miette::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not read {path:?}"))?;
+        Self::parse(&text).wrap_err_with(|| format!("Could not import {path:?}"))
+    }
+
+    pub fn parse(text: &str) -> miette::Result<Self> {
+        Ok(Self::try_parse(text)?)
+    }
+
+    fn try_parse(text: &str) -> Result<Self, ImportError> {
+        static PATTERN: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+            regex::Regex::new(r"patterns\[(\d+)\]\s*=\s*(\d+)'b([01xXzZ_]+)\s*;").unwrap()
+        });
+        static INPUT: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+            regex::Regex::new(
+                r"(\\\S+|[a-zA-Z_][a-zA-Z0-9$_]*)\s*=\s*patterns\[i\]\[(\d+)(?::(\d+))?\]\s*;",
+            )
+            .unwrap()
+        });
+        static OUTPUT: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+            regex::Regex::new(r"if\s*\(\s*(\\\S+|[a-zA-Z_][a-zA-Z0-9$_]*)\s*!==\s*patterns\[i\]\[(\d+)(?::(\d+))?\]\s*\)")
+                .unwrap()
+        });
+
+        let mut patterns = BTreeMap::new();
+        for captures in PATTERN.captures_iter(text) {
+            let index: usize = captures[1].parse().unwrap_or_default();
+            let bits = captures[3].replace('_', "").to_ascii_lowercase();
+            patterns.insert(index, bits);
+        }
+        if patterns.is_empty() {
+            return Err(ImportError::NoPatterns);
+        }
+
+        let signal = |captures: regex::Captures, output| {
+            let name = &captures[1];
+            let msb: usize = captures[2].parse().unwrap_or_default();
+            let lsb = captures
+                .get(3)
+                .and_then(|lsb| lsb.as_str().parse().ok())
+                .unwrap_or(msb);
+            ImportedSignal {
+                name: name.strip_prefix('\\').unwrap_or(name).to_string(),
+                msb,
+                lsb,
+                output,
+            }
+        };
+        let mut signals = INPUT
+            .captures_iter(text)
+            .map(|captures| signal(captures, false))
+            .chain(
+                OUTPUT
+                    .captures_iter(text)
+                    .map(|captures| signal(captures, true)),
+            )
+            .collect::<Vec<_>>();
+        if signals.is_empty() {
+            return Err(ImportError::NoSignals);
+        }
+        signals.sort_by_key(|signal| std::cmp::Reverse(signal.msb));
+        signals.dedup_by(|a, b| a.name == b.name);
+
+        let width = signals
+            .iter()
+            .map(|signal| signal.msb + 1)
+            .max()
+            .unwrap_or(0);
+        let rows = patterns
+            .into_iter()
+            .map(|(index, bits)| {
+                if bits.len() != width {
+                    return Err(ImportError::WrongWidth {
+                        index,
+                        found: bits.len(),
+                        expected: width,
+                    });
+                }
+                signals
+                    .iter()
+                    .map(|signal| {
+                        let bits = &bits[width - 1 - signal.msb..width - signal.lsb];
+                        parse_bits(bits).ok_or_else(|| ImportError::MixedBits {
+                            index,
+                            signal: signal.name.clone(),
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { signals, rows })
+    }
+
+    /// Replace the sequences of three rows which Digital uses for a clock pulse of the input
+    /// `clock` by a single row with a `C` in the clock column. A sequence is only replaced if
+    /// the clock is 0, 1, 0, all other inputs stay the same and only the last row checks outputs.
+    pub fn collapse_clock(&mut self, clock: &str) -> Result<(), ImportError> {
+        let Some(column) = self
+            .signals
+            .iter()
+            .position(|signal| signal.name == clock && !signal.output)
+        else {
+            return Err(ImportError::UnknownClock(clock.to_string()));
+        };
+
+        let is_pulse = |rows: &[Vec<ImportedValue>]| {
+            let [low, high, last] = rows else {
+                return false;
+            };
+            let clock_values = [low[column], high[column], last[column]];
+            clock_values == [0, 1, 0].map(ImportedValue::Value)
+                && self.signals.iter().enumerate().all(|(i, signal)| {
+                    if signal.output {
+                        low[i] == ImportedValue::X && high[i] == ImportedValue::X
+                    } else {
+                        i == column || (low[i] == high[i] && high[i] == last[i])
+                    }
+                })
+        };
+
+        let mut rows = vec![];
+        let mut i = 0;
+        while i < self.rows.len() {
+            if i + 3 <= self.rows.len() && is_pulse(&self.rows[i..i + 3]) {
+                let mut row = self.rows[i + 2].clone();
+                row[column] = ImportedValue::Clock;
+                rows.push(row);
+                i += 3;
+            } else {
+                rows.push(self.rows[i].clone());
+                i += 1;
+            }
+        }
+        self.rows = rows;
+        Ok(())
+    }
+
+    /// Write the test as Digital test data, which can be pasted into a test case
+    pub fn to_test_data(&self) -> String {
+        let names = self
+            .signals
+            .iter()
+            .map(|signal| signal.name.as_str())
+            .collect::<Vec<_>>();
+        format_test_data(&names, &self.rows)
+    }
+}
+
+/// Write a header and rows as Digital test data
+pub(crate) fn format_test_data(names: &[&str], rows: &[Vec<ImportedValue>]) -> String {
+    let mut lines = vec![names.join(" "), String::new()];
+    lines.extend(rows.iter().map(|row| {
+        row.iter()
+            .map(ImportedValue::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }));
+    lines.join("\n") + "\n"
+}
+
+/// Convert bits with the most significant bit first to a value. Returns `None` if some but not
+/// all bits are `x` or `z`.
+pub(crate) fn parse_bits(bits: &str) -> Option<ImportedValue> {
+    if bits.chars().all(|c| c == 'x') {
+        Some(ImportedValue::X)
+    } else if bits.chars().all(|c| c == 'z') {
+        Some(ImportedValue::Z)
+    } else {
+        i64::from_str_radix(bits, 2).ok().map(ImportedValue::Value)
+    }
+}
@@ -0,0 +1,136 @@
+use digital_test_runner::{dig, errors::IterStaticError, Signal, SignalType, TestCase};
+use serde::Serialize;
+
+/// A summary of a test case, as printed by the `list` command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestCaseInfo {
+    pub index: usize,
+    pub name: String,
+    pub kind: TestKind,
+    /// The number of rows after loops and clock cycles have been expanded. Only known for static tests.
+    pub rows: Option<usize>,
+    pub signals: Vec<SignalInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestKind {
+    /// The test does not depend on the output of the circuit and can be exported as a test bench
+    Static,
+    /// The test reads outputs of the circuit, eg, in a `while` loop
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignalInfo {
+    pub name: String,
+    pub bits: u64,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Input,
+    Output,
+    Bidirectional,
+    Virtual,
+}
+
+impl TestCaseInfo {
+    pub fn try_new(index: usize, name: &str, test_case: &TestCase) -> miette::Result<Self> {
+        let (kind, rows) = match test_case.try_iter_static() {
+            Ok(it) => {
+                let mut rows = 0;
+                for row in it {
+                    row?;
+                    rows += 1;
+                }
+                (TestKind::Static, Some(rows))
+            }
+            Err(IterStaticError::Dynamic) => (TestKind::Dynamic, None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            index,
+            name: name.to_string(),
+            kind,
+            rows,
+            signals: test_case.signals.iter().map(SignalInfo::from).collect(),
+        })
+    }
+}
+
+impl From<&Signal> for SignalInfo {
+    fn from(signal: &Signal) -> Self {
+        let direction = match signal.typ {
+            SignalType::Input { .. } => Direction::Input,
+            SignalType::Output => Direction::Output,
+            SignalType::Bidirectional { .. } => Direction::Bidirectional,
+            SignalType::Virtual { .. } => Direction::Virtual,
+        };
+        Self {
+            name: signal.name.clone(),
+            bits: signal.bits,
+            direction,
+        }
+    }
+}
+
+impl std::fmt::Display for TestCaseInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = if self.name.is_empty() {
+            "(unnamed)"
+        } else {
+            self.name.as_str()
+        };
+        match self.rows {
+            Some(1) => writeln!(f, "{}: {name} ({}, 1 row)", self.index, self.kind)?,
+            Some(rows) => writeln!(f, "{}: {name} ({}, {rows} rows)", self.index, self.kind)?,
+            None => writeln!(f, "{}: {name} ({})", self.index, self.kind)?,
+        }
+        for signal in &self.signals {
+            writeln!(f, "    {signal}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for SignalInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = match self.direction {
+            Direction::Input => "input",
+            Direction::Output => "output",
+            Direction::Bidirectional => "inout",
+            Direction::Virtual => "virtual",
+        };
+        if self.bits > 1 {
+            write!(f, "{direction:<7} {} [{}]", self.name, self.bits)
+        } else {
+            write!(f, "{direction:<7} {}", self.name)
+        }
+    }
+}
+
+impl std::fmt::Display for TestKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestKind::Static => write!(f, "static"),
+            TestKind::Dynamic => write!(f, "dynamic"),
+        }
+    }
+}
+
+/// Load every test case in `dig_file` and summarize it
+pub fn list_test_cases(dig_file: &dig::File) -> miette::Result<Vec<TestCaseInfo>> {
+    dig_file
+        .test_cases
+        .iter()
+        .enumerate()
+        .map(|(i, dig_test_case)| {
+            let test_case = dig_file.load_test(i)?;
+            TestCaseInfo::try_new(i, &dig_test_case.name, &test_case)
+        })
+        .collect()
+}
@@ -1,18 +1,82 @@
-use digital_test_runner::{
-    static_test::StaticDataRowIterator, ExpectedEntry, ExpectedValue, InputEntry, InputValue,
-    SignalType, TestCase,
-};
-use miette::IntoDiagnostic;
-use verilog::{VerilogIdentifier, VerilogValue};
+use digital_test_runner::{static_test::StaticDataRowIterator, ExpectedValue, TestCase};
+use miette::{IntoDiagnostic, WrapErr};
+use std::io::Write;
+use std::num::NonZeroUsize;
+
+macro_rules! outputln {
+    ($($t:tt)*) => {{
+        writeln!($($t)*).into_diagnostic()
+    }};
+}
 
+mod backend;
+mod batch;
+mod check;
+mod circuit;
+mod coverage;
+mod digital;
+mod export;
+mod filter;
+mod formal;
+mod hierarchy;
+mod import;
+mod info;
+mod netlist;
+mod reset;
+mod sample;
+mod sections;
+mod select;
+mod simulate;
+mod sva;
+mod vcd;
 mod verilog;
+mod waveform;
+
+pub use backend::TestbenchBackend;
+pub use batch::{output_file_name, Batch, GeneratedTest, Manifest, SkippedTest};
+pub use check::{ChecksFailed, VcdCheckBackend, WidthMismatch};
+pub use circuit::{
+    Attribute, Circuit, CircuitError, Element, InputElement, Point, Port, TestData, Wire,
+};
+pub use coverage::{BitToggles, Coverage, InputCoverage, OutputCoverage};
+pub use digital::{BidirectionalNotSupported, DigitalBackend, OptionNotSupported};
+pub use export::write_netlist;
+pub use filter::{RowFilter, SectionNotFound};
+pub use formal::{FormalBackend, FormalError, FormalMode, SbyFile};
+pub use hierarchy::Hierarchy;
+pub use import::{ImportError, ImportedSignal, ImportedTest, ImportedValue};
+pub use info::{list_test_cases, Direction, SignalInfo, TestCaseInfo, TestKind};
+pub use netlist::{Library, NetlistError};
+pub use reset::{unlisted_inputs, InitialInputs, ResetStep, UnknownInput};
+pub use sample::{sample_vcd, Sampling};
+pub use sections::{parse_sections, section_of_line, Section};
+pub use select::{
+    load_test_case, select_test_case, AvailableTestCases, SelectError, TestCaseSelector,
+};
+pub use simulate::{SimulationBackend, SimulationError, Simulator};
+pub use sva::{SvaBackend, ZeroSamplingDelay};
+pub use vcd::{Vcd, VcdError, VcdSignal};
+pub use verilog::{write_stub, DumpScope, VerilogBackend, WaveformDump};
+pub use waveform::VcdBackend;
 
 pub struct Builder<'a> {
     test_case: &'a TestCase,
     it: StaticDataRowIterator<'a>,
     output_path: Option<std::path::PathBuf>,
-    timescale: Option<String>,
-    delay: Option<(u32, u32)>,
+    verilog: VerilogBackend,
+    backend: Option<Box<dyn TestbenchBackend + 'a>>,
+    options: RowOptions,
+}
+
+/// Options controlling which rows and inputs are passed to the backend
+#[derive(Debug, Default)]
+struct RowOptions {
+    sections: Vec<Section>,
+    filter: RowFilter,
+    checkpoint: Option<NonZeroUsize>,
+    initial_inputs: Option<InitialInputs>,
+    input_defaults: Vec<InputElement>,
+    reset: Vec<ResetStep>,
 }
 
 impl<'a> Builder<'a> {
@@ -22,8 +86,9 @@ impl<'a> Builder<'a> {
             test_case,
             it,
             output_path: None,
-            timescale: None,
-            delay: None,
+            verilog: VerilogBackend::new(),
+            backend: None,
+            options: RowOptions::default(),
         })
     }
 
@@ -33,154 +98,176 @@ impl<'a> Builder<'a> {
     }
 
     pub fn with_timescale(mut self, timescale: impl Into<Option<String>>) -> Self {
-        self.timescale = timescale.into();
+        self.verilog = self.verilog.with_timescale(timescale);
         self
     }
 
     pub fn with_delay(mut self, delay: impl Into<Option<(u32, u32)>>) -> Self {
-        self.delay = delay.into();
+        self.verilog = self.verilog.with_delay(delay.into().unwrap_or((0, 10)));
+        self
+    }
+
+    /// Dump waveforms from the generated test bench
+    pub fn with_dump(mut self, dump: impl Into<Option<WaveformDump>>) -> Self {
+        self.verilog = self.verilog.with_dump(dump);
+        self
+    }
+
+    /// Stop the simulation once `max_errors` assertions have failed
+    pub fn with_max_errors(mut self, max_errors: impl Into<Option<usize>>) -> Self {
+        self.verilog = self.verilog.with_max_errors(max_errors);
+        self
+    }
+
+    /// Report progress and results for each section of the test program.
+    /// The sections can be found using [`parse_sections`].
+    pub fn with_sections(mut self, sections: Vec<Section>) -> Self {
+        self.options.sections = sections;
+        self
+    }
+
+    /// Only generate the rows matching `filter`. The first generated row sets all inputs,
+    /// so that the stimulus is correct even if earlier rows were skipped.
+    pub fn with_row_filter(mut self, filter: RowFilter) -> Self {
+        self.options.filter = filter;
         self
     }
 
-    pub fn done(self) -> miette::Result<()> {
-        output_verilog(
+    /// Set all inputs on every `every`th row instead of only the inputs which changed.
+    /// With `NonZeroUsize::MIN` every row sets all inputs.
+    pub fn with_checkpoint(mut self, every: impl Into<Option<NonZeroUsize>>) -> Self {
+        self.options.checkpoint = every.into();
+        self
+    }
+
+    /// Set all inputs at time zero, before the first row. This includes inputs which are never
+    /// set by the test.
+    pub fn with_initial_inputs(mut self, initial_inputs: impl Into<Option<InitialInputs>>) -> Self {
+        self.options.initial_inputs = initial_inputs.into();
+        self
+    }
+
+    /// Set the inputs which are not set by the test, see [`unlisted_inputs`], to their default
+    /// values from `inputs` at time zero. The defaults can be found using [`Circuit::inputs`].
+    pub fn with_input_defaults(mut self, inputs: Vec<InputElement>) -> Self {
+        self.options.input_defaults = inputs;
+        self
+    }
+
+    /// Run a reset sequence before the first row, after setting the initial inputs
+    pub fn with_reset(mut self, reset: Vec<ResetStep>) -> Self {
+        self.options.reset = reset;
+        self
+    }
+
+    /// Use `backend` to generate the output instead of the built-in Verilog backend.
+    /// Options such as the timescale and delay set on the builder only apply to the built-in backend.
+    pub fn with_backend(mut self, backend: impl TestbenchBackend + 'a) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Write the test bench to the configured output path, or to stdout if no path was given
+    pub fn done(mut self) -> miette::Result<()> {
+        if let Some(path) = self.output_path.take() {
+            let file = std::fs::File::create(&path)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Could not open file {path:?} for output"))?;
+            let mut out = std::io::BufWriter::new(file);
+            self.write_to(&mut out)?;
+            out.flush()
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Could not write to file {path:?}"))
+        } else {
+            self.write_to(&mut std::io::stdout().lock())
+        }
+    }
+
+    /// Write the test bench to `out`. Any output path set with [`Builder::with_output`] is ignored.
+    pub fn write_to(self, out: &mut impl Write) -> miette::Result<()> {
+        let mut backend = self.backend.unwrap_or_else(|| Box::new(self.verilog));
+        write_testbench(
             self.test_case,
             self.it,
-            self.output_path,
-            self.timescale,
-            self.delay.unwrap_or((0, 10)),
+            &self.options,
+            backend.as_mut(),
+            out,
         )
     }
-}
 
-macro_rules! outputln {
-    ($($t:tt)*) => {{
-        writeln!($($t)*).into_diagnostic()
-    }};
-}
-
-fn print_row<'a, Out: std::io::Write>(
-    line: usize,
-    out: &mut Out,
-    inputs: impl Iterator<Item = &'a InputEntry<'a>>,
-    outputs: impl Iterator<Item = &'a ExpectedEntry<'a>>,
-    delay: (u32, u32),
-) -> miette::Result<()> {
-    for input in inputs {
-        let identifier = VerilogIdentifier::from_input(input.signal);
-        let value = VerilogValue::from(input.value);
-        outputln!(out, "    {identifier} = {value};")?;
-    }
-    outputln!(out, "#{};", delay.0)?;
-    for output in outputs {
-        let identifier = VerilogIdentifier::from(output.signal);
-        let value = VerilogValue::from(output.value);
-        outputln!(out, "    `assert_eq({line}, {identifier}, {value});")?;
-    }
-    if delay.1 > 0 {
-        outputln!(out, "#{};", delay.1)?;
-    }
-    outputln!(out)?;
-    Ok(())
+    /// Return the test bench as a string. Any output path set with [`Builder::with_output`] is ignored.
+    pub fn to_string(self) -> miette::Result<String> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)?;
+        String::from_utf8(out).into_diagnostic()
+    }
 }
 
-fn output_verilog(
+fn write_testbench(
     test_case: &TestCase,
     it: StaticDataRowIterator,
-    path: Option<std::path::PathBuf>,
-    timescale: Option<String>,
-    delay: (u32, u32),
+    options: &RowOptions,
+    backend: &mut dyn TestbenchBackend,
+    out: &mut dyn Write,
 ) -> miette::Result<()> {
-    let mut out: Box<dyn std::io::Write> = if let Some(path) = path {
-        let Ok(file) = std::fs::File::create(&path) else {
-            miette::bail!("Could not open file {path:?} for output");
-        };
-        Box::new(file)
-    } else {
-        Box::new(std::io::stdout())
-    };
-    let out = &mut out;
-
-    if let Some(timescale) = timescale {
-        outputln!(out, "`timescale {timescale}\n")?;
-    }
-
-    outputln!(
-        out,
-        r#"`define assert_eq(line_num, signal, value) \
-    if (signal !== value) begin \
-        $display("ASSERTION FAILED on line line_num: signal != value"); \
-        error_count += 1; \
-    end"#
-    )?;
-    outputln!(out)?;
-
-    let ports = test_case
-        .signals
-        .iter()
-        .map(|sig| {
-            let io_type = match sig.typ {
-                SignalType::Input { .. } => "output reg",
-                SignalType::Output => "input",
-                SignalType::Bidirectional { .. } => "inout",
-                SignalType::Virtual { .. } => unreachable!(),
-            };
-            let width = if sig.bits > 1 {
-                format!("[{}:0] ", sig.bits - 1)
-            } else {
-                String::from("")
-            };
-            format!("    {io_type} {width}{}", VerilogIdentifier::from(sig))
-        })
-        .collect::<Vec<_>>()
-        .join(",\n");
-    outputln!(out, "module tb (\n{ports}\n);")?;
-    outputln!(out, "integer error_count = 0;")?;
-
-    for sig in &test_case.signals {
-        if sig.is_bidirectional() {
-            outputln!(
-                out,
-                "reg {} = {};",
-                VerilogIdentifier::from_input(sig),
-                VerilogValue::from(InputValue::Z)
-            )?;
-        }
+    let RowOptions {
+        sections,
+        filter,
+        checkpoint,
+        initial_inputs,
+        input_defaults,
+        reset,
+    } = options;
+
+    if !sections.is_empty() {
+        backend.set_sections(sections)?;
     }
+    backend.header(out, test_case)?;
+    backend.signal_declarations(out, &test_case.signals)?;
+    backend.begin(out)?;
 
-    for sig in &test_case.signals {
-        if sig.is_bidirectional() {
-            outputln!(
-                out,
-                "assign {} = {};",
-                VerilogIdentifier::from(sig),
-                VerilogIdentifier::from_input(sig)
-            )?;
-        }
+    let initial = reset::initial_values(test_case, initial_inputs.as_ref(), input_defaults)?;
+    if !initial.is_empty() {
+        backend.initial_inputs(out, &initial)?;
+    }
+    for step in reset {
+        backend.reset_step(out, &step.resolve(test_case)?)?;
     }
-    outputln!(out, "initial begin")?;
 
-    for row in it {
+    let mut current_section = None;
+    let mut previous_skipped = true;
+    for (index, row) in it.enumerate() {
         let row = row?;
-        print_row(
-            row.line,
-            out,
-            row.inputs.iter().filter(|inp| inp.changed),
-            row.expected
-                .iter()
-                .filter(|exp| exp.value != ExpectedValue::X),
-            delay,
-        )?;
+        if !filter.matches(index, row.line) {
+            previous_skipped = true;
+            continue;
+        }
+        let section = section_of_line(sections, row.line);
+        if section != current_section {
+            if let Some(index) = section {
+                backend.section(out, index, &sections[index])?;
+            }
+            current_section = section;
+        }
+        let all_inputs =
+            previous_skipped || checkpoint.is_some_and(|every| index % every.get() == 0);
+        let inputs = row
+            .inputs
+            .iter()
+            .filter(|inp| inp.changed || all_inputs)
+            .collect::<Vec<_>>();
+        let expected = row
+            .expected
+            .iter()
+            .filter(|exp| exp.value != ExpectedValue::X)
+            .collect::<Vec<_>>();
+        backend.stimulus(out, row.line, &inputs)?;
+        backend.check(out, row.line, &expected)?;
+        previous_skipped = false;
+        if filter.is_done(index) {
+            break;
+        }
     }
 
-    outputln!(out, "  if(error_count > 0) begin")?;
-    outputln!(out, "    $display(\"There were failed assertions\");")?;
-    outputln!(out, "    $finish_and_return(1);")?;
-    outputln!(out, "  end")?;
-    outputln!(out, "  $display(\"All tests passed.\");")?;
-
-    outputln!(out, "end")?;
-    outputln!(out, "endmodule")?;
-
-    Ok(())
+    backend.footer(out)
 }
@@ -1,20 +1,229 @@
 use digital_test_runner::dig;
+use digital_test_to_verilog::{
+    parse_sections, Circuit, Coverage, DigitalBackend, DumpScope, FormalBackend, FormalMode,
+    ImportedTest, InitialInputs, Library, ResetStep, RowFilter, Sampling, SbyFile,
+    SimulationBackend, Simulator, SvaBackend, TestCaseSelector, Vcd, VcdBackend, VcdCheckBackend,
+    WaveformDump,
+};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use miette::{IntoDiagnostic, WrapErr};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum TestCaseSelector {
-    Number(usize),
-    Name(String),
-}
-
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the test cases in a dig file together with their signals
+    List {
+        /// Path to dig file
+        file: PathBuf,
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Generate test benches for all test cases in a directory of dig files
+    Batch(BatchArgs),
+    /// Write an empty DUT module with the ports of a test case, as a starting point for the implementation
+    Stub(StubArgs),
+    /// Reconstruct Digital test data from a test bench exported by Digital
+    Import {
+        /// Path to the exported test bench, eg, "counter_tb.v"
+        file: PathBuf,
+        /// Replace the three rows Digital uses for a clock pulse of this input by a single row with a "C"
+        #[arg(long, value_name = "INPUT")]
+        clock: Option<String>,
+        /// Output file. By default the output is written to stdout.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Create Digital test data by sampling the signals of a VCD file
+    FromVcd(FromVcdArgs),
+    /// Check the inputs and outputs in a VCD file against a test case, without running a simulator
+    CheckVcd(CheckVcdArgs),
+    /// Report which input values and bit toggles a test case drives and which output states it checks
+    Coverage {
+        /// Path to dig file
+        file: PathBuf,
+        /// Select test case, see the main command. Optional if there is only a single test.
+        test: Option<TestCaseSelector>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run a test case on a simulation of the circuit, to check that the test passes before a test bench is generated
+    Simulate {
+        /// Path to dig file
+        file: PathBuf,
+        /// Select test case, see the main command. Optional if there is only a single test.
+        test: Option<TestCaseSelector>,
+        /// Directory to search for embedded circuits which are not found next to the circuit embedding them. May be given more than once.
+        #[arg(long, value_name = "DIR")]
+        library: Vec<PathBuf>,
+    },
+    /// Translate the circuit of a dig file to a Verilog module, as a replacement for exporting the DUT from Digital
+    Netlist(NetlistArgs),
+    /// Write a formal test bench with assumptions and assertions for SymbiYosys
+    Formal(FormalArgs),
+}
+
+#[derive(Args)]
+struct FormalArgs {
     /// Path to dig file
     file: PathBuf,
-    /// Select test case by name or (zero based) index. Optional if there is only a single test.
+    /// Select test case, see the main command. Optional if there is only a single test.
+    test: Option<TestCaseSelector>,
+    /// Output file. By default the output is written to stdout.
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Name of the DUT module. Defaults to the name of the dig file.
+    #[arg(long, value_name = "NAME")]
+    module: Option<String>,
+    /// How the rows are checked
+    #[arg(long, value_enum, default_value_t = FormalMode::Sequential)]
+    mode: FormalMode,
+    /// Also write a SymbiYosys configuration running a bounded model check of the test bench
+    #[arg(long, value_name = "FILE", requires = "output")]
+    sby: Option<PathBuf>,
+    /// Verilog source of the DUT to read in the SymbiYosys configuration. May be given more than once.
+    #[arg(long, value_name = "FILE", requires = "sby")]
+    source: Vec<PathBuf>,
+    /// A step of a reset sequence run before the first row, see the main command
+    #[arg(long, value_name = "STEP")]
+    reset: Vec<ResetStep>,
+}
+
+#[derive(Args)]
+struct NetlistArgs {
+    /// Path to dig file. Embedded circuits are looked up in the same directory.
+    file: PathBuf,
+    /// Output file. By default the output is written to stdout.
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Name of the module. Defaults to the name of the dig file.
+    #[arg(long, value_name = "NAME")]
+    module: Option<String>,
+    /// Directory to search for embedded circuits which are not found next to the circuit embedding them. May be given more than once.
+    #[arg(long, value_name = "DIR")]
+    library: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct CheckVcdArgs {
+    /// Path to dig file
+    file: PathBuf,
+    /// Select test case, see the main command. Optional if there is only a single test.
+    test: Option<TestCaseSelector>,
+    /// Path to the VCD file to check
+    #[arg(long, value_name = "FILE")]
+    vcd: PathBuf,
+    /// Scope of the signals in the VCD file, eg, "top.dut". By default signals are found by a unique suffix of their name.
+    #[arg(long)]
+    scope: Option<String>,
+    /// Delay after setting inputs and after reading outputs, see the main command. The delays are in the time unit of the VCD file.
+    #[arg(long, short, default_value = "10:0", value_parser = parse_delay)]
+    delay: (u32, u32),
+    /// Time in the VCD file at which the inputs of the first row are set
+    #[arg(long, value_name = "TIME", default_value_t = 0)]
+    start: u64,
+    /// A step of a reset sequence run before the first row, see the main command
+    #[arg(long, value_name = "STEP")]
+    reset: Vec<ResetStep>,
+}
+
+#[derive(Args)]
+#[command(group(clap::ArgGroup::new("sampling").required(true).args(["clock", "interval"])))]
+struct FromVcdArgs {
+    /// Path to VCD file
+    file: PathBuf,
+    /// Inputs to include in the test data, separated by commas. Signals are found by their full name, eg, "top.dut.A", or by a unique suffix such as "A" or "dut.A".
+    #[arg(long, value_name = "SIGNALS", value_delimiter = ',', required = true)]
+    inputs: Vec<String>,
+    /// Outputs to include in the test data, separated by commas
+    #[arg(long, value_name = "SIGNALS", value_delimiter = ',')]
+    outputs: Vec<String>,
+    /// Create a row for every rising edge of this clock, with a "C" in the clock column
+    #[arg(long, value_name = "SIGNAL")]
+    clock: Option<String>,
+    /// Create a row every N time units of the VCD file
+    #[arg(long, value_name = "N")]
+    interval: Option<std::num::NonZeroU64>,
+    /// Time of the first row with --interval. Defaults to the interval.
+    #[arg(long, value_name = "TIME", requires = "interval")]
+    offset: Option<u64>,
+    /// Output file. By default the output is written to stdout.
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct StubArgs {
+    /// Path to dig file
+    file: PathBuf,
+    /// Select test case, see the main command. Optional if there is only a single test.
+    test: Option<TestCaseSelector>,
+    /// Output file. By default the output is written to stdout.
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Name of the module. Defaults to the name of the dig file.
+    #[arg(long, value_name = "NAME")]
+    module: Option<String>,
+}
+
+#[derive(Args)]
+struct BatchArgs {
+    /// Directory to search recursively for dig files
+    dir: PathBuf,
+    /// Directory to write the test benches and manifest.json to
+    #[arg(long, short, value_name = "DIR")]
+    output: PathBuf,
+    /// Only include dig files whose path relative to the input directory matches this glob pattern. May be given more than once.
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<glob::Pattern>,
+    /// Exclude dig files whose path relative to the input directory matches this glob pattern. May be given more than once.
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<glob::Pattern>,
+    /// Only generate test benches for test cases matched by this selector
+    #[arg(long)]
+    test: Option<TestCaseSelector>,
+    /// Verilog timescale, eg, 10ns or 1us/1us
+    #[arg(long, short, value_parser = parse_timescale)]
+    timescale: Option<String>,
+    /// Delay after setting inputs and after reading outputs, see the main command
+    #[arg(long, short, default_value = "10:0", value_parser = parse_delay)]
+    delay: (u32, u32),
+    /// Also write a Makefile which compiles and runs the test benches with Icarus Verilog. The DUT for "foo.dig" and the circuits embedded in it is read from "foo.v" in the same directory.
+    #[arg(long)]
+    makefile: bool,
+    /// Additional Verilog source to compile with every test bench in the Makefile. May be given more than once.
+    #[arg(long, value_name = "FILE", requires = "makefile")]
+    source: Vec<PathBuf>,
+    /// Also generate test benches for the test cases of embedded circuits, in a directory named after the dig file embedding them. Embedded circuits in the input directory are only generated there.
+    #[arg(long)]
+    hierarchy: bool,
+    /// Directory to search for embedded circuits which are not found next to the circuit embedding them. May be given more than once.
+    #[arg(long, value_name = "DIR", requires = "hierarchy")]
+    library: Vec<PathBuf>,
+    /// Leave inputs which are not set by a test undriven instead of setting them to their default values, see the main command
+    #[arg(long)]
+    no_input_defaults: bool,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Path to dig file
+    #[arg(required = true)]
+    file: Option<PathBuf>,
+    /// Select test case by name or (zero based) index. Use a "re:" or "glob:" prefix to select by regular expression or glob pattern. Optional if there is only a single test.
     test: Option<TestCaseSelector>,
     /// Output file. By default the output is written to stdout.
     #[arg(long, short, value_name = "FILE")]
@@ -25,15 +234,68 @@ struct Cli {
     /// An argument such as "10:5" means dealy 10 ticks after setting inputs and 5 ticks after reading outputs. The second value is optional and defaults to zero.
     #[arg(long, short, default_value = "10:0", value_parser = parse_delay)]
     delay: (u32, u32),
+    /// Report progress and the number of failures for each section of the test, where sections are started by comment lines such as "# reset"
+    #[arg(long)]
+    sections: bool,
+    /// Stop the test bench at the first failed assertion
+    #[arg(long, conflicts_with = "max_errors")]
+    fail_fast: bool,
+    /// Stop the test bench after this many failed assertions
+    #[arg(long, value_name = "N")]
+    max_errors: Option<std::num::NonZeroUsize>,
+    /// Dump waveforms to a VCD file when the test bench is run
+    #[arg(long, value_name = "FILE", group = "dump_file", conflicts_with = "fst")]
+    vcd: Option<String>,
+    /// Dump waveforms to an FST file when the test bench is run. This only changes the file name passed to $dumpfile, so Icarus Verilog needs to be run as "vvp -fst" to actually write an FST file.
+    #[arg(long, value_name = "FILE", group = "dump_file")]
+    fst: Option<String>,
+    /// Which signals to dump
+    #[arg(long, value_enum, default_value_t = DumpScope::All, requires = "dump_file")]
+    dump_scope: DumpScope,
+    /// Stop dumping this many rows after the first failed assertion. The rows leading up to the failure are dumped as well.
+    #[arg(long, value_name = "ROWS", requires = "dump_file")]
+    dump_after_failure: Option<usize>,
+    /// Only generate rows from this range of (one based) source lines, eg, "10:20", "10:" or ":20"
+    #[arg(long, value_name = "FIRST:LAST", value_parser = parse_line_range)]
+    lines: Option<RangeInclusive<usize>>,
+    /// Only generate rows from this range of (zero based) row indices, counted after loops are expanded
+    #[arg(long, value_name = "FIRST:LAST", value_parser = parse_range)]
+    rows: Option<RangeInclusive<usize>>,
+    /// Only generate the rows of the section started by the comment line "# NAME"
+    #[arg(long, value_name = "NAME")]
+    section: Option<String>,
+    /// Set all inputs on every row instead of only the inputs which changed
+    #[arg(long, conflicts_with = "checkpoint")]
+    all_inputs: bool,
+    /// Set all inputs on every Nth row instead of only the inputs which changed
+    #[arg(long, value_name = "N")]
+    checkpoint: Option<std::num::NonZeroUsize>,
+    /// Set all inputs at time zero, including inputs which are never set by the test
+    #[arg(long, value_enum, value_name = "VALUES", num_args = 0..=1, default_missing_value = "zero")]
+    init_inputs: Option<InitValues>,
+    /// Write a test bench in the style of the test benches exported by Digital, which instantiates the DUT and stops at the first failure
+    #[arg(long, conflicts_with_all = ["sections", "fail_fast", "max_errors", "dump_file", "all_inputs", "checkpoint", "init_inputs", "reset"])]
+    digital_compat: bool,
+    /// Check the outputs with concurrent assertions in a separate checker module, which is bound to the DUT as exported by Digital, instead of in the stimulus
+    #[arg(long, conflicts_with_all = ["sections", "fail_fast", "max_errors", "dump_file", "digital_compat"])]
+    sva: bool,
+    /// Leave inputs which are not set by the test undriven instead of setting them to their default values
+    #[arg(long)]
+    no_input_defaults: bool,
+    /// A step of a reset sequence run before the first row, eg, "~CLR=0,CLK=1". May be given more than once.
+    #[arg(long, value_name = "STEP")]
+    reset: Vec<ResetStep>,
+    /// Also write a VCD file with the stimulus and the expected outputs of the test, for comparison with the simulated waveforms
+    #[arg(long, value_name = "FILE")]
+    expected_vcd: Option<PathBuf>,
 }
 
-impl std::str::FromStr for TestCaseSelector {
-    type Err = &'static str; // The actual type doesn't matter since we never error, but it must implement `Display`
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(s.parse::<usize>()
-            .map(Self::Number)
-            .unwrap_or_else(|_| Self::Name(s.to_string())))
-    }
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum InitValues {
+    /// Set inputs to zero and bidirectional signals to high impedance
+    Zero,
+    /// Use the default values of the inputs in the circuit
+    Default,
 }
 
 fn parse_timescale(s: &str) -> Result<String, String> {
@@ -72,48 +334,350 @@ fn parse_delay(s: &str) -> Result<(u32, u32), String> {
     Ok((d1, d2))
 }
 
+fn parse_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    parse_range_from(s, 0)
+}
+
+/// Parse a range of one based line numbers
+fn parse_line_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+    parse_range_from(s, 1)
+}
+
+fn parse_range_from(s: &str, min: usize) -> Result<RangeInclusive<usize>, String> {
+    let Some((s1, s2)) = s.split_once(':') else {
+        return Err(String::from("expected a range such as 10:20"));
+    };
+    let parse = |s: &str, default| {
+        if s.is_empty() {
+            return Ok(default);
+        }
+        let n = s
+            .parse()
+            .map_err(|_| format!("expected an integer, found {s}"))?;
+        if n < min {
+            Err(format!("expected an integer of at least {min}, found {n}"))
+        } else {
+            Ok(n)
+        }
+    };
+    let (first, last) = (parse(s1, min)?, parse(s2, usize::MAX)?);
+    if first > last {
+        return Err(format!(
+            "the range is empty, since {first} is greater than {last}"
+        ));
+    }
+    Ok(first..=last)
+}
+
 fn main() -> miette::Result<()> {
     let cli = Cli::parse();
 
-    let path = cli.file;
-    eprintln!("Loading {path:?}");
+    match cli.command {
+        Some(Command::List { file, json }) => list(file, json),
+        Some(Command::Batch(args)) => batch(args),
+        Some(Command::Stub(args)) => stub(args),
+        Some(Command::Import {
+            file,
+            clock,
+            output,
+        }) => import(file, clock, output),
+        Some(Command::FromVcd(args)) => from_vcd(args),
+        Some(Command::CheckVcd(args)) => check_vcd(args),
+        Some(Command::Simulate {
+            file,
+            test,
+            library,
+        }) => simulate(file, test, library),
+        Some(Command::Coverage { file, test, json }) => coverage(file, test, json),
+        Some(Command::Netlist(args)) => netlist(args),
+        Some(Command::Formal(args)) => formal(args),
+        None => generate(cli.generate),
+    }
+}
+
+fn list(path: PathBuf, json: bool) -> miette::Result<()> {
     let dig_file = dig::File::open(&path)?;
+    let test_cases = digital_test_to_verilog::list_test_cases(&dig_file)?;
 
-    let test_num = match cli.test {
-        Some(TestCaseSelector::Number(test_num)) => test_num,
-        Some(TestCaseSelector::Name(name)) => {
-            if let Some(test_num) = dig_file
-                .test_cases
-                .iter()
-                .position(|test_case| test_case.name == name)
-            {
-                test_num
-            } else {
-                miette::bail!("No test case \"{name}\" found");
-            }
+    if json {
+        let s = serde_json::to_string_pretty(&test_cases).into_diagnostic()?;
+        println!("{s}");
+    } else {
+        for test_case in &test_cases {
+            print!("{test_case}");
         }
-        None => {
-            if dig_file.test_cases.len() == 1 {
-                0
-            } else {
-                eprintln!("There are more than one test case in {path:?}");
-                for (i, test_case) in dig_file.test_cases.iter().enumerate() {
-                    if test_case.name.is_empty() {
-                        eprintln!("{i}: (unnamed)");
-                    } else {
-                        eprintln!("{i}: {}", test_case.name);
-                    }
-                }
-                miette::bail!("Please specify a test case");
-            }
+    }
+    Ok(())
+}
+
+fn coverage(path: PathBuf, test: Option<TestCaseSelector>, json: bool) -> miette::Result<()> {
+    let dig_file = dig::File::open(&path)?;
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, test.as_ref())?;
+    let test_case = dig_file.load_test(test_num)?;
+    let coverage = Coverage::try_new(&test_case)?;
+
+    if json {
+        let s = serde_json::to_string_pretty(&coverage).into_diagnostic()?;
+        println!("{s}");
+    } else {
+        print!("{coverage}");
+    }
+    Ok(())
+}
+
+fn batch(args: BatchArgs) -> miette::Result<()> {
+    let BatchArgs {
+        dir,
+        output,
+        include,
+        exclude,
+        test,
+        timescale,
+        delay,
+        makefile,
+        source,
+        hierarchy,
+        library,
+        no_input_defaults,
+    } = args;
+
+    let manifest = digital_test_to_verilog::Batch::new(dir, &output)
+        .with_include(include)
+        .with_exclude(exclude)
+        .with_selector(test)
+        .with_makefile(makefile)
+        .with_sources(source)
+        .with_hierarchy(hierarchy.then(|| Library::default().with_search_path(library)))
+        .with_input_defaults(!no_input_defaults)
+        .with_builder_options(move |builder| {
+            builder.with_delay(delay).with_timescale(timescale.clone())
+        })
+        .run()?;
+
+    eprintln!(
+        "Generated {} test benches in {output:?}, skipped {}",
+        manifest.generated.len(),
+        manifest.skipped.len()
+    );
+    for skipped in &manifest.skipped {
+        match (&skipped.name, skipped.index) {
+            (Some(name), Some(index)) => eprintln!(
+                "Skipped {:?} #{index} ({name}): {}",
+                skipped.dig, skipped.reason
+            ),
+            _ => eprintln!("Skipped {:?}: {}", skipped.dig, skipped.reason),
         }
+    }
+    Ok(())
+}
+
+/// The name of the module generated by Digital for a dig file
+fn module_name(path: &std::path::Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn stub(args: StubArgs) -> miette::Result<()> {
+    let StubArgs {
+        file,
+        test,
+        output,
+        module,
+    } = args;
+
+    let dig_file = dig::File::open(&file)?;
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, test.as_ref())?;
+    let test_case = dig_file.load_test(test_num)?;
+
+    let module = module.unwrap_or_else(|| module_name(&file));
+
+    if let Some(path) = output {
+        let mut out = std::fs::File::create(&path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not open file {path:?} for output"))?;
+        digital_test_to_verilog::write_stub(&test_case, &module, &mut out)
+    } else {
+        digital_test_to_verilog::write_stub(&test_case, &module, &mut std::io::stdout().lock())
+    }
+}
+
+fn check_vcd(args: CheckVcdArgs) -> miette::Result<()> {
+    let dig_file = dig::File::open(&args.file)?;
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, args.test.as_ref())?;
+    let test_case = dig_file.load_test(test_num)?;
+    let vcd = Vcd::open(&args.vcd)?;
+
+    digital_test_to_verilog::Builder::try_new(&test_case)?
+        .with_backend(
+            VcdCheckBackend::new(vcd)
+                .with_scope(args.scope)
+                .with_delay(args.delay)
+                .with_start_time(args.start),
+        )
+        .with_reset(args.reset)
+        .done()
+}
+
+fn simulate(
+    path: PathBuf,
+    test: Option<TestCaseSelector>,
+    library: Vec<PathBuf>,
+) -> miette::Result<()> {
+    let dig_file = dig::File::open(&path)?;
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, test.as_ref())?;
+    let test_case = dig_file.load_test(test_num)?;
+    let library = Library::for_file(&path).with_search_path(library);
+    let simulator = Simulator::new(&Circuit::open(&path)?, &library)?;
+
+    digital_test_to_verilog::Builder::try_new(&test_case)?
+        .with_backend(SimulationBackend::new(simulator))
+        .done()
+}
+
+fn netlist(args: NetlistArgs) -> miette::Result<()> {
+    let NetlistArgs {
+        file,
+        output,
+        module,
+        library,
+    } = args;
+
+    let circuit = Circuit::open(&file)?;
+    let library = Library::for_file(&file).with_search_path(library);
+    let module = module.unwrap_or_else(|| module_name(&file));
+
+    if let Some(path) = output {
+        let mut out = std::fs::File::create(&path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not open file {path:?} for output"))?;
+        digital_test_to_verilog::write_netlist(&circuit, &module, &library, &mut out)
+    } else {
+        let mut out = std::io::stdout().lock();
+        digital_test_to_verilog::write_netlist(&circuit, &module, &library, &mut out)
+    }
+}
+
+fn formal(args: FormalArgs) -> miette::Result<()> {
+    let FormalArgs {
+        file,
+        test,
+        output,
+        module,
+        mode,
+        sby,
+        source,
+        reset,
+    } = args;
+
+    let dig_file = dig::File::open(&file)?;
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, test.as_ref())?;
+    let test_case = dig_file.load_test(test_num)?;
+    let circuit = Circuit::open(&file)?;
+    let module = module.unwrap_or_else(|| module_name(&file));
+
+    let sby = match (sby, &output) {
+        (Some(path), Some(output)) => {
+            let mut sources = source
+                .iter()
+                .map(|path| {
+                    std::fs::canonicalize(path)
+                        .into_diagnostic()
+                        .wrap_err_with(|| format!("Could not find source file {path:?}"))
+                })
+                .collect::<miette::Result<Vec<_>>>()?;
+            sources.push(std::env::current_dir().into_diagnostic()?.join(output));
+            Some(SbyFile { path, sources })
+        }
+        _ => None,
+    };
+
+    digital_test_to_verilog::Builder::try_new(&test_case)?
+        .with_backend(
+            FormalBackend::new(module)
+                .with_mode(mode)
+                .with_sby(sby)
+                .with_ports(circuit.ports()),
+        )
+        .with_reset(reset)
+        .with_output(output)
+        .done()
+}
+
+/// Write `text` to the file `output`, or to stdout if no file is given
+fn write_text(output: Option<PathBuf>, text: &str) -> miette::Result<()> {
+    if let Some(path) = output {
+        std::fs::write(&path, text)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not write to file {path:?}"))
+    } else {
+        print!("{text}");
+        Ok(())
+    }
+}
+
+fn import(path: PathBuf, clock: Option<String>, output: Option<PathBuf>) -> miette::Result<()> {
+    let mut test = ImportedTest::open(&path)?;
+    if let Some(clock) = clock {
+        test.collapse_clock(&clock)?;
+    }
+    let test_data = test.to_test_data();
+
+    write_text(output, &test_data)
+}
+
+fn from_vcd(args: FromVcdArgs) -> miette::Result<()> {
+    let vcd = Vcd::open(&args.file)?;
+    let sampling = match (args.clock, args.interval) {
+        (Some(clock), _) => Sampling::Clock(clock),
+        (None, Some(interval)) => Sampling::Interval {
+            interval: interval.get(),
+            offset: args.offset.unwrap_or(interval.get()),
+        },
+        (None, None) => unreachable!("a sampling argument is required"),
     };
+    let test_data =
+        digital_test_to_verilog::sample_vcd(&vcd, &args.inputs, &args.outputs, &sampling)?;
+
+    write_text(args.output, &test_data)
+}
+
+fn generate(cli: GenerateArgs) -> miette::Result<()> {
+    let path = cli.file.expect("the file argument is required");
+    eprintln!("Loading {path:?}");
+    let dig_file = dig::File::open(&path)?;
+
+    let test_num = digital_test_to_verilog::select_test_case(&dig_file, cli.test.as_ref())?;
 
     eprintln!(
         "Loading test case #{test_num}: {}",
         dig_file.test_cases[test_num].name
     );
     let test_case = dig_file.load_test(test_num)?;
+    let circuit = Circuit::open(&path)?;
+
+    let sections = if cli.sections || cli.section.is_some() {
+        circuit
+            .test_data()
+            .get(test_num)
+            .map(|test_data| parse_sections(&test_data.source))
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    let mut filter = RowFilter::new();
+    if let Some(lines) = cli.lines {
+        filter = filter.with_lines(lines);
+    }
+    if let Some(rows) = cli.rows {
+        filter = filter.with_rows(rows);
+    }
+    if let Some(name) = &cli.section {
+        filter = filter.with_section(&sections, name)?;
+    }
+    let sections = if cli.sections { sections } else { vec![] };
 
     let builder = digital_test_to_verilog::Builder::try_new(&test_case)?;
 
@@ -121,9 +685,89 @@ fn main() -> miette::Result<()> {
         eprintln!("Writing output to {path:?}");
     }
 
+    let max_errors = if cli.fail_fast {
+        Some(1)
+    } else {
+        cli.max_errors.map(std::num::NonZeroUsize::get)
+    };
+
+    let checkpoint = if cli.all_inputs {
+        Some(std::num::NonZeroUsize::MIN)
+    } else {
+        cli.checkpoint
+    };
+
+    let initial_inputs = match cli.init_inputs {
+        None => None,
+        Some(InitValues::Zero) => Some(InitialInputs::Zero),
+        Some(InitValues::Default) => Some(InitialInputs::Defaults(circuit.inputs())),
+    };
+
+    // The test benches exported by Digital have no way to set inputs at time zero
+    let input_defaults = if cli.no_input_defaults || cli.digital_compat {
+        vec![]
+    } else {
+        let unlisted = digital_test_to_verilog::unlisted_inputs(&test_case)?;
+        if !unlisted.is_empty() {
+            let names = unlisted
+                .iter()
+                .map(|signal| signal.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            eprintln!("Warning: The test does not set the inputs {names}. They are set to their default values.");
+        }
+        circuit.inputs()
+    };
+
+    let dump = cli.vcd.or(cli.fst).map(|file| WaveformDump {
+        file,
+        scope: cli.dump_scope,
+        after_failure: cli.dump_after_failure,
+    });
+
+    if let Some(expected_vcd) = &cli.expected_vcd {
+        eprintln!("Writing expected waveforms to {expected_vcd:?}");
+        digital_test_to_verilog::Builder::try_new(&test_case)?
+            .with_backend(
+                VcdBackend::new()
+                    .with_timescale(cli.timescale.clone())
+                    .with_delay(cli.delay),
+            )
+            .with_row_filter(filter.clone())
+            .with_initial_inputs(initial_inputs.clone())
+            .with_input_defaults(input_defaults.clone())
+            .with_reset(cli.reset.clone())
+            .with_output(expected_vcd.clone())
+            .done()?;
+    }
+
+    let builder = if cli.digital_compat {
+        builder.with_backend(
+            DigitalBackend::new(module_name(&path))
+                .with_timescale(cli.timescale.clone())
+                .with_delay(cli.delay),
+        )
+    } else if cli.sva {
+        builder.with_backend(
+            SvaBackend::new(module_name(&path))
+                .with_timescale(cli.timescale.clone())
+                .with_delay(cli.delay),
+        )
+    } else {
+        builder
+    };
+
     builder
         .with_delay(cli.delay)
         .with_timescale(cli.timescale)
+        .with_dump(dump)
+        .with_max_errors(max_errors)
+        .with_sections(sections)
+        .with_row_filter(filter)
+        .with_checkpoint(checkpoint)
+        .with_initial_inputs(initial_inputs)
+        .with_input_defaults(input_defaults)
+        .with_reset(cli.reset)
         .with_output(cli.output)
         .done()
 }
@@ -0,0 +1,555 @@
+use digital_test_runner::InputValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::circuit::{Attribute, Circuit, Element, Point};
+use crate::verilog::mask;
+
+/// The grid size of Digital, which is the distance between neighbouring pins
+const SIZE: i64 = 20;
+
+/// Elements without pins, or whose pins are not connected to anything the netlist uses
+const IGNORED_ELEMENTS: &[&str] = &["Testcase", "Text", "Rectangle", "PowerSupply"];
+
+/// Where to find the circuits embedded in a circuit, such as `74779-inc.dig` in `74779.dig`.
+///
+/// Like in Digital, an embedded circuit is looked up relative to the circuit embedding it.
+/// If it is not found there, the directories of the search path are tried in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Library {
+    dir: PathBuf,
+    search_path: Vec<PathBuf>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum NetlistError {
+    #[error("Element {0} is not supported")]
+    #[diagnostic(help(
+        "The supported elements are In, Out, Clock, Const, Ground, VDD, And, Or, NAnd, NOr, XOr, XNOr, Not, Multiplexer, Splitter, Driver, D_FF, Add, Comparator and embedded circuits"
+    ))]
+    UnsupportedElement(String),
+    #[error("The attribute {attribute} of {element} is not supported")]
+    UnsupportedAttribute { element: String, attribute: String },
+    #[error("Invalid number of selector bits {0}")]
+    InvalidSelectorBits(i64),
+    #[error("Invalid splitting \"{0}\" of a splitter")]
+    #[diagnostic(help("Expected a list of widths such as \"4,4\" or \"1*8\""))]
+    InvalidSplitting(String),
+    #[error("Could not find the embedded circuit {name}")]
+    SubcircuitNotFound {
+        name: String,
+        #[help]
+        searched: String,
+    },
+    #[error("The circuit {0} embeds itself")]
+    RecursiveSubcircuit(String),
+}
+
+impl Library {
+    /// Look up the circuits embedded in a circuit in the directory `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            search_path: vec![],
+        }
+    }
+
+    /// Look up the circuits embedded in the dig file `path` relative to it
+    pub fn for_file(path: impl AsRef<Path>) -> Self {
+        Self::new(path.as_ref().parent().unwrap_or(Path::new(".")))
+    }
+
+    /// Also look up embedded circuits in these directories
+    pub fn with_search_path(mut self, dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.search_path.extend(dirs);
+        self
+    }
+
+    /// Find the dig file `name` embedded in a circuit in the directory `dir`
+    pub(crate) fn find(&self, name: &str, dir: &Path) -> Result<PathBuf, NetlistError> {
+        let dirs = std::iter::once(dir).chain(self.search_path.iter().map(PathBuf::as_path));
+        dirs.clone()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| NetlistError::SubcircuitNotFound {
+                name: name.to_string(),
+                searched: format!(
+                    "Searched in {}",
+                    dirs.map(|dir| format!("{dir:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })
+    }
+}
+
+/// Resolves the circuits embedded in a circuit to their dig files with a [`Library`], keeping
+/// track of the dig files being loaded to detect a circuit which embeds itself
+#[derive(Debug, Clone)]
+pub(crate) struct Resolver<'a> {
+    library: &'a Library,
+    /// The dig files currently being loaded, outermost first, with their canonical paths
+    stack: Vec<(PathBuf, PathBuf)>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(library: &'a Library) -> Self {
+        Self {
+            library,
+            stack: vec![],
+        }
+    }
+
+    /// Find the dig file `file_name` embedded in the circuit entered last, or in the directory
+    /// of the library if no circuit was entered
+    pub(crate) fn resolve(&self, file_name: &str) -> Result<PathBuf, NetlistError> {
+        let dir = match self.stack.last() {
+            Some((path, _)) => path.parent().unwrap_or(Path::new(".")),
+            None => &self.library.dir,
+        };
+        let path = self.library.find(file_name, dir)?;
+        let canonical = canonical(&path);
+        if self.stack.iter().any(|(_, parent)| *parent == canonical) {
+            return Err(NetlistError::RecursiveSubcircuit(file_name.to_string()));
+        }
+        Ok(path)
+    }
+
+    /// Start loading the dig file `path`, whose embedded circuits are resolved relative to it
+    pub(crate) fn enter(&mut self, path: &Path) {
+        self.stack.push((path.to_path_buf(), canonical(path)));
+    }
+
+    /// Finish loading the dig file entered last
+    pub(crate) fn leave(&mut self) {
+        self.stack.pop();
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A circuit as a list of components connected by numbered nets
+#[derive(Debug, Clone)]
+pub(crate) struct Netlist {
+    pub(crate) components: Vec<Component>,
+    /// The width of each net, which is the width of the widest pin connected to it
+    pub(crate) nets: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Component {
+    pub(crate) kind: Kind,
+    pub(crate) label: Option<String>,
+    pub(crate) bits: u64,
+    /// The nets connected to the input pins, in the order of the pins of the element in Digital
+    pub(crate) inputs: Vec<usize>,
+    /// The nets connected to the output pins
+    pub(crate) outputs: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Kind {
+    Input(InputValue),
+    Output,
+    Const(u64),
+    Gate { gate: Gate, invert: bool },
+    Not,
+    Multiplexer,
+    Splitter { inputs: Vec<u64>, outputs: Vec<u64> },
+    Driver,
+    DFlipFlop,
+    Add,
+    Comparator { signed: bool },
+    Subcircuit(Rc<Subcircuit>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Gate {
+    And,
+    Or,
+    XOr,
+}
+
+/// An embedded circuit, whose inputs and outputs are the pins of the element
+#[derive(Debug, Clone)]
+pub(crate) struct Subcircuit {
+    /// The name of the dig file without extension
+    pub(crate) name: String,
+    /// The width of the shape of the element in grid units
+    width: i64,
+    pub(crate) netlist: Netlist,
+}
+
+/// A pin relative to the position of an element before rotation
+#[derive(Debug, Clone, Copy)]
+struct Pin {
+    x: i64,
+    y: i64,
+    bits: u64,
+}
+
+fn pin(x: i64, y: i64, bits: u64) -> Pin {
+    Pin { x, y, bits }
+}
+
+/// Parse the `Input Splitting` or `Output Splitting` of a splitter, eg, `4,4` or `1*8`
+fn parse_splitting(splitting: &str) -> Result<Vec<u64>, NetlistError> {
+    let invalid = || NetlistError::InvalidSplitting(splitting.to_string());
+    let mut widths = vec![];
+    for part in splitting.split(',') {
+        let (bits, count) = match part.split_once('*') {
+            Some((bits, count)) => (bits, count.trim().parse().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let bits: u64 = bits.trim().parse().map_err(|_| invalid())?;
+        if bits == 0 || count == 0 {
+            return Err(invalid());
+        }
+        widths.extend(std::iter::repeat_n(bits, count));
+    }
+    Ok(widths)
+}
+
+/// The pins of the generic shape Digital uses for gates and most other elements: the inputs
+/// on the left and the outputs on the right, `width` grid units apart. A single output is
+/// centered, leaving a gap in the middle of an even number of inputs.
+fn generic_pins(inputs: &[u64], outputs: &[u64], width: i64) -> (Vec<Pin>, Vec<Pin>) {
+    let symmetric = outputs.len() == 1;
+    let count = inputs.len() as i64;
+    let inputs = inputs
+        .iter()
+        .zip(0..)
+        .map(|(&bits, i)| {
+            let gap = symmetric && count % 2 == 0 && i >= count / 2;
+            pin(0, i * SIZE + if gap { SIZE } else { 0 }, bits)
+        })
+        .collect();
+    let offset = if symmetric { count / 2 * SIZE } else { 0 };
+    let outputs = outputs
+        .iter()
+        .zip(0..)
+        .map(|(&bits, i)| pin(width * SIZE, i * SIZE + offset, bits))
+        .collect();
+    (inputs, outputs)
+}
+
+type Shape = (Kind, u64, (Vec<Pin>, Vec<Pin>));
+
+struct Builder<'a> {
+    resolver: Resolver<'a>,
+    /// The embedded circuits loaded so far by their dig files
+    subcircuits: HashMap<PathBuf, Rc<Subcircuit>>,
+}
+
+impl Builder<'_> {
+    /// The component for an element together with its pins, following the shapes of Digital
+    fn shape(&mut self, element: &Element) -> miette::Result<Option<Shape>> {
+        let inverted = matches!(
+            element.attributes.get("inverterConfig"),
+            Some(Attribute::InverterConfig(inputs)) if !inputs.is_empty()
+        );
+        let unsupported = [
+            ("inverterConfig", inverted),
+            (
+                "wideShape",
+                element.bool_attribute("wideShape") == Some(true),
+            ),
+            (
+                "flipSelPos",
+                element.bool_attribute("flipSelPos") == Some(true),
+            ),
+        ];
+        if let Some((attribute, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(NetlistError::UnsupportedAttribute {
+                element: element.name.clone(),
+                attribute: attribute.to_string(),
+            }
+            .into());
+        }
+        let bits = element.bits();
+        let inputs = element.int_attribute("Inputs").unwrap_or(2).max(1) as usize;
+        let gate = |gate, invert| {
+            let width = if invert { 4 } else { 3 };
+            let pins = generic_pins(&vec![bits; inputs], &[bits], width);
+            (Kind::Gate { gate, invert }, bits, pins)
+        };
+        let single = |kind, input: bool| {
+            let pins = vec![pin(0, 0, bits)];
+            if input {
+                (kind, bits, (pins, vec![]))
+            } else {
+                (kind, bits, (vec![], pins))
+            }
+        };
+
+        let shape = match element.name.as_str() {
+            "In" | "Clock" => single(Kind::Input(element.input_default()), false),
+            "Out" => single(Kind::Output, true),
+            "Const" => {
+                let value = element.int_attribute("Value").unwrap_or(1) as u64 & mask(bits);
+                single(Kind::Const(value), false)
+            }
+            "Ground" => single(Kind::Const(0), false),
+            "VDD" => single(Kind::Const(mask(bits)), false),
+            "And" => gate(Gate::And, false),
+            "NAnd" => gate(Gate::And, true),
+            "Or" => gate(Gate::Or, false),
+            "NOr" => gate(Gate::Or, true),
+            "XOr" => gate(Gate::XOr, false),
+            "XNOr" => gate(Gate::XOr, true),
+            "Not" => (
+                Kind::Not,
+                bits,
+                (vec![pin(0, 0, bits)], vec![pin(2 * SIZE, 0, bits)]),
+            ),
+            "Multiplexer" => {
+                let select_bits = element.int_attribute("Selector Bits").unwrap_or(1);
+                let count = u32::try_from(select_bits)
+                    .ok()
+                    .filter(|&bits| bits > 0)
+                    .and_then(|bits| 1i64.checked_shl(bits))
+                    .filter(|&count| count > 0)
+                    .ok_or(NetlistError::InvalidSelectorBits(select_bits))?;
+                let data = if count == 2 {
+                    vec![pin(0, 0, bits), pin(0, 2 * SIZE, bits)]
+                } else {
+                    (0..count).map(|i| pin(0, i * SIZE, bits)).collect()
+                };
+                let select = pin(SIZE, count * SIZE, select_bits as u64);
+                let pins = std::iter::once(select).chain(data).collect();
+                let output = pin(2 * SIZE, count / 2 * SIZE, bits);
+                (Kind::Multiplexer, bits, (pins, vec![output]))
+            }
+            "Splitter" => {
+                let splitting = |key| parse_splitting(element.string_attribute(key).unwrap_or("1"));
+                let inputs = splitting("Input Splitting")?;
+                let outputs = splitting("Output Splitting")?;
+                let pins = (
+                    inputs
+                        .iter()
+                        .zip(0..)
+                        .map(|(&b, i)| pin(0, i * SIZE, b))
+                        .collect(),
+                    outputs
+                        .iter()
+                        .zip(0..)
+                        .map(|(&b, i)| pin(SIZE, i * SIZE, b))
+                        .collect(),
+                );
+                (Kind::Splitter { inputs, outputs }, bits, pins)
+            }
+            "Driver" => (
+                Kind::Driver,
+                bits,
+                (
+                    vec![pin(-SIZE, 0, bits), pin(0, -SIZE, 1)],
+                    vec![pin(SIZE, 0, bits)],
+                ),
+            ),
+            "D_FF" => (
+                Kind::DFlipFlop,
+                bits,
+                generic_pins(&[bits, 1], &[bits, bits], 3),
+            ),
+            "Add" => (
+                Kind::Add,
+                bits,
+                generic_pins(&[bits, bits, 1], &[bits, 1], 3),
+            ),
+            "Comparator" => (
+                Kind::Comparator {
+                    signed: element.bool_attribute("Signed").unwrap_or(false),
+                },
+                bits,
+                generic_pins(&[bits, bits], &[1, 1, 1], 3),
+            ),
+            name if name.ends_with(".dig") => {
+                let subcircuit = self.subcircuit(name)?;
+                let widths = |inputs| {
+                    subcircuit
+                        .netlist
+                        .ports(inputs)
+                        .map(|port| port.bits)
+                        .collect::<Vec<_>>()
+                };
+                let pins = generic_pins(&widths(true), &widths(false), subcircuit.width);
+                (Kind::Subcircuit(subcircuit), 1, pins)
+            }
+            name if IGNORED_ELEMENTS.contains(&name) => return Ok(None),
+            name => return Err(NetlistError::UnsupportedElement(name.to_string()).into()),
+        };
+        Ok(Some(shape))
+    }
+
+    fn subcircuit(&mut self, file_name: &str) -> miette::Result<Rc<Subcircuit>> {
+        let name = file_name
+            .strip_suffix(".dig")
+            .unwrap_or(file_name)
+            .to_string();
+        let path = self.resolver.resolve(file_name)?;
+        if let Some(subcircuit) = self.subcircuits.get(&path) {
+            return Ok(subcircuit.clone());
+        }
+        let circuit = Circuit::open(&path)?;
+        let width = match circuit.attributes.get("Width") {
+            Some(Attribute::Int(width)) => *width,
+            _ => 3,
+        };
+        self.resolver.enter(&path);
+        let netlist = self.netlist(&circuit);
+        self.resolver.leave();
+        let subcircuit = Rc::new(Subcircuit {
+            name,
+            width,
+            netlist: netlist?,
+        });
+        self.subcircuits.insert(path, subcircuit.clone());
+        Ok(subcircuit)
+    }
+
+    fn netlist(&mut self, circuit: &Circuit) -> miette::Result<Netlist> {
+        let mut nets = Nets::default();
+        let mut components = vec![];
+        let mut widths: Vec<(usize, u64)> = vec![];
+        for element in &circuit.elements {
+            let Some((kind, bits, (inputs, outputs))) = self.shape(element)? else {
+                continue;
+            };
+            let mut connect = |pins: Vec<Pin>| {
+                pins.into_iter()
+                    .map(|pin| {
+                        let index = nets.point(pin_position(element, pin));
+                        widths.push((index, pin.bits));
+                        index
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let inputs = connect(inputs);
+            let outputs = connect(outputs);
+            components.push(Component {
+                kind,
+                label: element.label().map(str::to_string),
+                bits,
+                inputs,
+                outputs,
+            });
+        }
+
+        for wire in &circuit.wires {
+            let (a, b) = (nets.point(wire.p1), nets.point(wire.p2));
+            nets.union(a, b);
+        }
+        let points = nets
+            .points
+            .iter()
+            .map(|(&point, &index)| (point, index))
+            .collect::<Vec<_>>();
+        for wire in &circuit.wires {
+            let a = nets.point(wire.p1);
+            for &(point, index) in &points {
+                if is_inside(point, wire.p1, wire.p2) {
+                    nets.union(a, index);
+                }
+            }
+        }
+
+        // Number the nets which are connected to pins consecutively
+        let mut numbers = HashMap::new();
+        for component in &mut components {
+            for pin in component.inputs.iter_mut().chain(&mut component.outputs) {
+                let root = nets.find(*pin);
+                let next = numbers.len();
+                *pin = *numbers.entry(root).or_insert(next);
+            }
+        }
+        let mut net_widths = vec![1; numbers.len()];
+        for (index, bits) in widths {
+            let net = numbers[&nets.find(index)];
+            net_widths[net] = net_widths[net].max(bits);
+        }
+
+        Ok(Netlist {
+            components,
+            nets: net_widths,
+        })
+    }
+}
+
+impl Netlist {
+    /// Connect the elements of `circuit` by its wires. Embedded circuits are looked up in
+    /// `library`.
+    pub(crate) fn new(circuit: &Circuit, library: &Library) -> miette::Result<Self> {
+        Builder {
+            resolver: Resolver::new(library),
+            subcircuits: HashMap::new(),
+        }
+        .netlist(circuit)
+    }
+
+    /// The labelled inputs or outputs, in the order of the elements in the circuit
+    pub(crate) fn ports(&self, inputs: bool) -> impl Iterator<Item = &Component> {
+        self.components
+            .iter()
+            .filter(move |component| match component.kind {
+                Kind::Input(_) => inputs,
+                Kind::Output => !inputs,
+                _ => false,
+            })
+    }
+}
+
+/// The position of a pin after rotating the element by its `rotation` attribute
+fn pin_position(element: &Element, pin: Pin) -> Point {
+    let rotation = match element.attributes.get("rotation") {
+        Some(Attribute::Rotation(rotation)) => *rotation,
+        _ => 0,
+    };
+    let (x, y) = (0..rotation % 4).fold((pin.x, pin.y), |(x, y), _| (y, -x));
+    Point {
+        x: element.pos.x + x,
+        y: element.pos.y + y,
+    }
+}
+
+/// Whether `p` lies on the wire from `a` to `b`, excluding its ends
+fn is_inside(p: Point, a: Point, b: Point) -> bool {
+    let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let dot = (p.x - a.x) * (b.x - a.x) + (p.y - a.y) * (b.y - a.y);
+    let length = (b.x - a.x).pow(2) + (b.y - a.y).pow(2);
+    cross == 0 && 0 < dot && dot < length
+}
+
+/// Group points into nets, where points are connected by wires, including points which lie on
+/// a wire between its ends
+#[derive(Default)]
+struct Nets {
+    points: HashMap<Point, usize>,
+    parents: Vec<usize>,
+}
+
+impl Nets {
+    fn point(&mut self, point: Point) -> usize {
+        let next = self.parents.len();
+        let index = *self.points.entry(point).or_insert(next);
+        if index == next {
+            self.parents.push(next);
+        }
+        index
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a] = b;
+    }
+}
@@ -0,0 +1,162 @@
+use digital_test_runner::{InputValue, Signal, SignalType, TestCase};
+use std::collections::HashSet;
+
+use crate::InputElement;
+
+/// The values of the inputs at time zero, before the first row
+#[derive(Debug, Clone, PartialEq)]
+pub enum InitialInputs {
+    /// Set inputs to zero and bidirectional signals to high impedance
+    Zero,
+    /// Set inputs to the default values of the matching `In` elements,
+    /// see [`Circuit::inputs`](crate::Circuit::inputs). Other inputs are set as for `Zero`.
+    Defaults(Vec<InputElement>),
+}
+
+/// A step of the reset sequence run before the first row. The inputs are set, followed by the
+/// same delays as for a row of the test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetStep {
+    pub inputs: Vec<(String, InputValue)>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("The reset sequence sets {name:?}, which is not an input of the test case")]
+pub struct UnknownInput {
+    pub name: String,
+}
+
+impl InitialInputs {
+    /// The initial value of every input and bidirectional signal of `test_case`
+    pub(crate) fn values<'a>(&self, test_case: &'a TestCase) -> Vec<(&'a Signal, InputValue)> {
+        test_case
+            .signals
+            .iter()
+            .filter_map(|signal| {
+                let zero = match signal.typ {
+                    SignalType::Input { .. } => InputValue::Value(0),
+                    SignalType::Bidirectional { .. } => InputValue::Z,
+                    _ => return None,
+                };
+                let value = match self {
+                    InitialInputs::Zero => zero,
+                    InitialInputs::Defaults(inputs) => inputs
+                        .iter()
+                        .find(|input| input.name == signal.name)
+                        .map_or(zero, |input| input.default),
+                };
+                Some((signal, value))
+            })
+            .collect()
+    }
+}
+
+/// The inputs and bidirectional signals of `test_case` which are not set by the test,
+/// because they are not part of the header of the test data
+pub fn unlisted_inputs(test_case: &TestCase) -> miette::Result<Vec<&Signal>> {
+    let mut listed = HashSet::new();
+    for row in test_case.try_iter_static()? {
+        for input in row?.inputs {
+            listed.insert(input.signal.name.as_str());
+        }
+    }
+    Ok(test_case
+        .signals
+        .iter()
+        .filter(|signal| is_input(signal) && !listed.contains(signal.name.as_str()))
+        .collect())
+}
+
+/// The values to set at time zero: all inputs if `initial_inputs` is given, and the unlisted
+/// inputs with a default value in `defaults`
+pub(crate) fn initial_values<'a>(
+    test_case: &'a TestCase,
+    initial_inputs: Option<&InitialInputs>,
+    defaults: &[InputElement],
+) -> miette::Result<Vec<(&'a Signal, InputValue)>> {
+    let mut values = initial_inputs
+        .map(|initial_inputs| initial_inputs.values(test_case))
+        .unwrap_or_default();
+    if defaults.is_empty() {
+        return Ok(values);
+    }
+
+    for signal in unlisted_inputs(test_case)? {
+        let Some(input) = defaults.iter().find(|input| input.name == signal.name) else {
+            continue;
+        };
+        match values.iter_mut().find(|(s, _)| s.name == signal.name) {
+            Some((_, value)) => *value = input.default,
+            None => values.push((signal, input.default)),
+        }
+    }
+    Ok(values)
+}
+
+fn is_input(signal: &Signal) -> bool {
+    matches!(
+        signal.typ,
+        SignalType::Input { .. } | SignalType::Bidirectional { .. }
+    )
+}
+
+impl ResetStep {
+    /// Look up the signals set by this step in `test_case`
+    pub(crate) fn resolve<'a>(
+        &self,
+        test_case: &'a TestCase,
+    ) -> Result<Vec<(&'a Signal, InputValue)>, UnknownInput> {
+        self.inputs
+            .iter()
+            .map(|(name, value)| {
+                test_case
+                    .signals
+                    .iter()
+                    .find(|signal| &signal.name == name && is_input(signal))
+                    .map(|signal| (signal, *value))
+                    .ok_or_else(|| UnknownInput { name: name.clone() })
+            })
+            .collect()
+    }
+}
+
+impl std::str::FromStr for ResetStep {
+    type Err = String;
+
+    /// Parse a step such as `~CLR=0,CLK=1`. The assignments are separated by commas or spaces.
+    /// Values are decimal, hexadecimal with a `0x` prefix, binary with a `0b` prefix, or `Z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let inputs = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|assignment| !assignment.is_empty())
+            .map(|assignment| {
+                let Some((name, value)) = assignment.split_once('=') else {
+                    return Err(format!(
+                        "expected an assignment such as A=1, found {assignment}"
+                    ));
+                };
+                Ok((name.trim().to_string(), parse_input_value(value.trim())?))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if inputs.is_empty() {
+            return Err(String::from("unexpected empty reset step"));
+        }
+        Ok(Self { inputs })
+    }
+}
+
+fn parse_input_value(s: &str) -> Result<InputValue, String> {
+    if s.eq_ignore_ascii_case("z") {
+        return Ok(InputValue::Z);
+    }
+    let value = if let Some(hex) = s.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(bin) = s.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2)
+    } else {
+        s.parse()
+    };
+    value
+        .map(InputValue::Value)
+        .map_err(|_| format!("expected a number or Z, found {s}"))
+}
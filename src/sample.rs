@@ -0,0 +1,133 @@
+use crate::import::{format_test_data, parse_bits};
+use crate::{ImportedValue, Vcd, VcdError, VcdSignal};
+
+/// When to sample the signals of a VCD to create a row of test data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sampling {
+    /// Create a row with a `C` in the column of this clock for every rising edge. Inputs are
+    /// sampled just before the edge and outputs just before the next edge.
+    Clock(String),
+    /// Sample all signals just before `offset`, `offset + interval`, `offset + 2 * interval` and
+    /// so on, up to the end of the dump. An input with a rising edge since the previous sample
+    /// which is low again at the sample is written as `C`. The other inputs of such a row are
+    /// sampled just before the edge.
+    Interval { interval: u64, offset: u64 },
+}
+
+/// Create Digital test data from the signals `inputs` and `outputs` of a VCD.
+/// The signals are looked up using [`Vcd::find`] and the header uses the names without scope.
+pub fn sample_vcd(
+    vcd: &Vcd,
+    inputs: &[String],
+    outputs: &[String],
+    sampling: &Sampling,
+) -> Result<String, VcdError> {
+    let mut input_signals = find_all(vcd, inputs)?;
+    let output_signals = find_all(vcd, outputs)?;
+
+    let (names, rows) = match sampling {
+        Sampling::Clock(clock) => {
+            let clock_signal = vcd.find(clock)?;
+            // The clock has its own column, so it is dropped if it is also given as an input
+            let inputs = inputs
+                .iter()
+                .zip(&input_signals)
+                .filter(|(_, signal)| signal.name != clock_signal.name)
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            input_signals.retain(|signal| signal.name != clock_signal.name);
+            let edges = clock_signal.rising_edges();
+            let names = std::iter::once(clock)
+                .chain(inputs)
+                .chain(outputs)
+                .map(|name| leaf_name(name))
+                .collect::<Vec<_>>();
+            let rows = edges
+                .iter()
+                .enumerate()
+                .map(|(i, &edge)| {
+                    let inputs = input_signals
+                        .iter()
+                        .map(|signal| to_value(signal.value_before(edge)));
+                    let outputs = output_signals.iter().map(|signal| {
+                        to_value(match edges.get(i + 1) {
+                            Some(&next) => signal.value_before(next),
+                            None => signal.value_at(vcd.end_time),
+                        })
+                    });
+                    std::iter::once(ImportedValue::Clock)
+                        .chain(inputs)
+                        .chain(outputs)
+                        .collect()
+                })
+                .collect::<Vec<_>>();
+            (names, rows)
+        }
+        Sampling::Interval { interval, offset } => {
+            let names = inputs
+                .iter()
+                .chain(outputs)
+                .map(|name| leaf_name(name))
+                .collect::<Vec<_>>();
+            let interval = (*interval).max(1);
+            let input_edges = input_signals
+                .iter()
+                .map(|signal| signal.rising_edges())
+                .collect::<Vec<_>>();
+            let rows = (0..)
+                .map(|i| offset + i * interval)
+                .take_while(|&time| time <= vcd.end_time)
+                .map(|time| {
+                    let since = time.saturating_sub(interval);
+                    // The rising edge of each input which is clocked in this row
+                    let clock_edges = input_signals
+                        .iter()
+                        .zip(&input_edges)
+                        .map(|(signal, edges)| {
+                            if signal.width == 1 && signal.value_before(time) == Some("0") {
+                                edges
+                                    .iter()
+                                    .copied()
+                                    .find(|&edge| since <= edge && edge < time)
+                            } else {
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    // Digital sets the inputs of a row before the clock pulse, so they are
+                    // sampled just before the first edge
+                    let input_time = clock_edges.iter().flatten().min().copied().unwrap_or(time);
+                    let inputs = input_signals
+                        .iter()
+                        .zip(&clock_edges)
+                        .map(|(signal, edge)| {
+                            if edge.is_some() {
+                                ImportedValue::Clock
+                            } else {
+                                to_value(signal.value_before(input_time))
+                            }
+                        });
+                    let outputs = output_signals
+                        .iter()
+                        .map(|signal| to_value(signal.value_before(time)));
+                    inputs.chain(outputs).collect()
+                })
+                .collect::<Vec<_>>();
+            (names, rows)
+        }
+    };
+
+    Ok(format_test_data(&names, &rows))
+}
+
+fn find_all<'a>(vcd: &'a Vcd, names: &[String]) -> Result<Vec<&'a VcdSignal>, VcdError> {
+    names.iter().map(|name| vcd.find(name)).collect()
+}
+
+fn leaf_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+fn to_value(bits: Option<&str>) -> ImportedValue {
+    bits.and_then(parse_bits).unwrap_or(ImportedValue::X)
+}
@@ -0,0 +1,27 @@
+/// A part of a test program started by a comment line, such as `# reset`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    /// The (one based) line number of the comment
+    pub line: usize,
+}
+
+/// Find the sections of a test program. Every line consisting only of a comment starts a new section.
+pub fn parse_sections(source: &str) -> Vec<Section> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let name = line.trim().strip_prefix('#')?.trim();
+            (!name.is_empty()).then(|| Section {
+                name: name.to_string(),
+                line: i + 1,
+            })
+        })
+        .collect()
+}
+
+/// Return the index of the section containing `line`, if any
+pub fn section_of_line(sections: &[Section], line: usize) -> Option<usize> {
+    sections.iter().rposition(|section| section.line < line)
+}
@@ -0,0 +1,171 @@
+use digital_test_runner::{dig, TestCase};
+use std::path::Path;
+
+/// Selects test cases in a dig file by index or name
+#[derive(Debug, Clone)]
+pub enum TestCaseSelector {
+    /// Select the test case with the given zero based index
+    Index(usize),
+    /// Select the test case with exactly this name
+    Name(String),
+    /// Select test cases with names matching a regular expression
+    Regex(regex::Regex),
+    /// Select test cases with names matching a glob pattern, eg, `A*`
+    Glob(glob::Pattern),
+}
+
+/// The test cases in a dig file, listed by index and name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableTestCases(pub Vec<(usize, String)>);
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum SelectError {
+    #[error("The file contains no test cases")]
+    NoTestCases,
+    #[error("There is more than one test case. Please specify a test case")]
+    Unspecified {
+        #[help]
+        available: AvailableTestCases,
+    },
+    #[error("No test case {selector} found")]
+    NotFound {
+        selector: String,
+        #[help]
+        available: AvailableTestCases,
+    },
+    #[error("More than one test case {selector} found")]
+    Ambiguous {
+        selector: String,
+        #[help]
+        matching: AvailableTestCases,
+    },
+}
+
+impl TestCaseSelector {
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            TestCaseSelector::Index(n) => *n == index,
+            TestCaseSelector::Name(s) => s == name,
+            TestCaseSelector::Regex(re) => re.is_match(name),
+            TestCaseSelector::Glob(pattern) => pattern.matches(name),
+        }
+    }
+
+    /// Return the indices of all test cases in `dig_file` matched by the selector
+    pub fn select_all(&self, dig_file: &dig::File) -> Vec<usize> {
+        dig_file
+            .test_cases
+            .iter()
+            .enumerate()
+            .filter(|(i, test_case)| self.matches(*i, &test_case.name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl std::str::FromStr for TestCaseSelector {
+    type Err = String;
+
+    /// Parse a selector. A number selects by index, a `re:` prefix selects by regular expression,
+    /// a `glob:` prefix selects by glob pattern and anything else selects by exact name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(n) = s.parse::<usize>() {
+            Ok(Self::Index(n))
+        } else if let Some(re) = s.strip_prefix("re:") {
+            regex::Regex::new(re)
+                .map(Self::Regex)
+                .map_err(|err| err.to_string())
+        } else if let Some(pattern) = s.strip_prefix("glob:") {
+            glob::Pattern::new(pattern)
+                .map(Self::Glob)
+                .map_err(|err| err.to_string())
+        } else {
+            Ok(Self::Name(s.to_string()))
+        }
+    }
+}
+
+impl std::fmt::Display for TestCaseSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestCaseSelector::Index(n) => write!(f, "#{n}"),
+            TestCaseSelector::Name(s) => write!(f, "\"{s}\""),
+            TestCaseSelector::Regex(re) => write!(f, "matching /{re}/"),
+            TestCaseSelector::Glob(pattern) => write!(f, "matching '{pattern}'"),
+        }
+    }
+}
+
+impl AvailableTestCases {
+    fn from_indices(dig_file: &dig::File, indices: impl IntoIterator<Item = usize>) -> Self {
+        Self(
+            indices
+                .into_iter()
+                .map(|i| (i, dig_file.test_cases[i].name.clone()))
+                .collect(),
+        )
+    }
+
+    fn all(dig_file: &dig::File) -> Self {
+        Self::from_indices(dig_file, 0..dig_file.test_cases.len())
+    }
+}
+
+impl std::fmt::Display for AvailableTestCases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The available test cases are:")?;
+        for (i, name) in &self.0 {
+            if name.is_empty() {
+                write!(f, "\n{i}: (unnamed)")?;
+            } else {
+                write!(f, "\n{i}: {name}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find the index of the test case in `dig_file` selected by `selector`.
+///
+/// If `selector` is `None` the file must contain exactly one test case.
+pub fn select_test_case(
+    dig_file: &dig::File,
+    selector: Option<&TestCaseSelector>,
+) -> Result<usize, SelectError> {
+    if dig_file.test_cases.is_empty() {
+        return Err(SelectError::NoTestCases);
+    }
+
+    let Some(selector) = selector else {
+        if dig_file.test_cases.len() == 1 {
+            return Ok(0);
+        } else {
+            return Err(SelectError::Unspecified {
+                available: AvailableTestCases::all(dig_file),
+            });
+        }
+    };
+
+    let indices = selector.select_all(dig_file);
+    match (selector, indices.as_slice()) {
+        (_, []) => Err(SelectError::NotFound {
+            selector: selector.to_string(),
+            available: AvailableTestCases::all(dig_file),
+        }),
+        (_, [i]) => Ok(*i),
+        _ => Err(SelectError::Ambiguous {
+            selector: selector.to_string(),
+            matching: AvailableTestCases::from_indices(dig_file, indices.iter().copied()),
+        }),
+    }
+}
+
+/// Open the dig file at `path` and load the test case selected by `selector`
+pub fn load_test_case(
+    path: impl AsRef<Path>,
+    selector: Option<&TestCaseSelector>,
+) -> miette::Result<TestCase> {
+    let dig_file = dig::File::open(path.as_ref())?;
+    let test_num = select_test_case(&dig_file, selector)?;
+    Ok(dig_file.load_test(test_num)?)
+}
@@ -0,0 +1,410 @@
+use digital_test_runner::{ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, TestCase};
+use miette::IntoDiagnostic;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::circuit::Circuit;
+use crate::netlist::{Gate, Kind, Netlist};
+use crate::verilog::{binary, mask};
+use crate::{ChecksFailed, Library, TestbenchBackend};
+
+/// The number of evaluations of all components before a circuit is considered to oscillate
+const MAX_ITERATIONS: usize = 1000;
+
+/// A simulation of a circuit with the basic elements of Digital, which can be used to check that
+/// a test passes before a test bench is generated for it. Embedded circuits are simulated as if
+/// their elements were part of the circuit.
+///
+/// All components switch without delay. After the inputs have been set,
+/// [`settle`](Simulator::settle) evaluates the components until no value changes any more.
+/// Like in Digital, all values are zero initially and flip-flops change their state at the
+/// rising edge of their clock. Gates read high impedance inputs as zero.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    components: Vec<Component>,
+    /// The value of each net, where `None` is high impedance
+    values: Vec<Option<u64>>,
+    /// The component of each input by label
+    inputs: HashMap<String, usize>,
+    /// The net and width of each output by label
+    outputs: HashMap<String, (usize, u64)>,
+}
+
+#[derive(Debug, Clone)]
+struct Component {
+    kind: Kind,
+    /// The label of an input or output of the simulated circuit
+    label: Option<String>,
+    bits: u64,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    /// The value of an input or the state of a flip-flop
+    state: Option<u64>,
+    /// The clock of a flip-flop at the last evaluation
+    clock: u64,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum SimulationError {
+    #[error("The circuit does not settle")]
+    #[diagnostic(help("The circuit may contain a combinational loop"))]
+    Oscillation,
+    #[error("A net is driven by more than one output with different values")]
+    ShortCircuit,
+    #[error("There is no input called {0}")]
+    UnknownInput(String),
+    #[error("There is no output called {0}")]
+    UnknownOutput(String),
+}
+
+/// Add the components of `netlist` to `components`, replacing embedded circuits by their
+/// components. `nets` maps the nets of `netlist` to the nets of the simulation and `net_count`
+/// is the number of nets of the simulation so far. The inputs and outputs of embedded circuits
+/// are left out since their nets are the nets of the pins of the embedding element.
+fn flatten(
+    netlist: &Netlist,
+    nets: &[usize],
+    net_count: &mut usize,
+    top: bool,
+    components: &mut Vec<Component>,
+) {
+    for component in &netlist.components {
+        let inputs = component
+            .inputs
+            .iter()
+            .map(|&net| nets[net])
+            .collect::<Vec<_>>();
+        let outputs = component
+            .outputs
+            .iter()
+            .map(|&net| nets[net])
+            .collect::<Vec<_>>();
+        let state = match &component.kind {
+            Kind::Input(_) | Kind::Output if !top => continue,
+            Kind::Subcircuit(subcircuit) => {
+                let mut sub_nets = vec![None; subcircuit.netlist.nets.len()];
+                for (port, &net) in subcircuit.netlist.ports(true).zip(&inputs) {
+                    sub_nets[port.outputs[0]] = Some(net);
+                }
+                for (port, &net) in subcircuit.netlist.ports(false).zip(&outputs) {
+                    sub_nets[port.inputs[0]].get_or_insert(net);
+                }
+                let sub_nets = sub_nets
+                    .into_iter()
+                    .map(|net| {
+                        net.unwrap_or_else(|| {
+                            *net_count += 1;
+                            *net_count - 1
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                flatten(&subcircuit.netlist, &sub_nets, net_count, false, components);
+                continue;
+            }
+            Kind::Input(InputValue::Value(value)) => Some(*value as u64 & mask(component.bits)),
+            Kind::Input(InputValue::Z) => None,
+            _ => Some(0),
+        };
+        components.push(Component {
+            kind: component.kind.clone(),
+            label: component.label.clone().filter(|_| top),
+            bits: component.bits,
+            inputs,
+            outputs,
+            state,
+            clock: 0,
+        });
+    }
+}
+
+impl Simulator {
+    /// Simulate `circuit`, looking up embedded circuits in `library`
+    pub fn new(circuit: &Circuit, library: &Library) -> miette::Result<Self> {
+        let netlist = Netlist::new(circuit, library)?;
+        let mut net_count = netlist.nets.len();
+        let nets = (0..net_count).collect::<Vec<_>>();
+        let mut components = vec![];
+        flatten(&netlist, &nets, &mut net_count, true, &mut components);
+
+        let mut inputs = HashMap::new();
+        let mut outputs = HashMap::new();
+        for (index, component) in components.iter().enumerate() {
+            let Some(label) = component.label.clone() else {
+                continue;
+            };
+            match component.kind {
+                Kind::Input(_) => {
+                    inputs.insert(label, index);
+                }
+                Kind::Output => {
+                    outputs.insert(label, (component.inputs[0], component.bits));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            components,
+            values: vec![Some(0); net_count],
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Set the input with the label `name`. The new value is only seen by the other components
+    /// after [`settle`](Simulator::settle).
+    pub fn set_input(&mut self, name: &str, value: InputValue) -> Result<(), SimulationError> {
+        let Some(&index) = self.inputs.get(name) else {
+            return Err(SimulationError::UnknownInput(name.to_string()));
+        };
+        let component = &mut self.components[index];
+        component.state = match value {
+            InputValue::Value(value) => Some(value as u64 & mask(component.bits)),
+            InputValue::Z => None,
+        };
+        Ok(())
+    }
+
+    /// The value of the output with the label `name`, where `None` is high impedance
+    pub fn output(&self, name: &str) -> Result<Option<u64>, SimulationError> {
+        let Some(&(net, bits)) = self.outputs.get(name) else {
+            return Err(SimulationError::UnknownOutput(name.to_string()));
+        };
+        Ok(self.values[net].map(|value| value & mask(bits)))
+    }
+
+    /// Evaluate all components until the values of the nets do not change any more
+    pub fn settle(&mut self) -> Result<(), SimulationError> {
+        for _ in 0..MAX_ITERATIONS {
+            let mut values = vec![None; self.values.len()];
+            let mut short_circuit = false;
+            for component in &mut self.components {
+                let outputs = component.evaluate(&self.values);
+                for (&net, value) in component.outputs.iter().zip(outputs) {
+                    match (values[net], value) {
+                        (Some(old), Some(new)) if old != new => short_circuit = true,
+                        (None, Some(new)) => values[net] = Some(new),
+                        _ => {}
+                    }
+                }
+            }
+            if values == self.values {
+                return if short_circuit {
+                    Err(SimulationError::ShortCircuit)
+                } else {
+                    Ok(())
+                };
+            }
+            self.values = values;
+        }
+        Err(SimulationError::Oscillation)
+    }
+}
+
+impl Component {
+    /// Compute the outputs from the values of the nets. Flip-flops update their state if their
+    /// clock has risen since the last evaluation.
+    fn evaluate(&mut self, values: &[Option<u64>]) -> Vec<Option<u64>> {
+        let net = |i: usize| values[self.inputs[i]];
+        let value = |i: usize| net(i).unwrap_or(0);
+        let all = mask(self.bits);
+        match &self.kind {
+            Kind::Input(_) => vec![self.state],
+            Kind::Output | Kind::Subcircuit(_) => vec![],
+            Kind::Const(value) => vec![Some(*value)],
+            Kind::Gate { gate, invert } => {
+                let result = (0..self.inputs.len())
+                    .map(value)
+                    .reduce(|a, b| match gate {
+                        Gate::And => a & b,
+                        Gate::Or => a | b,
+                        Gate::XOr => a ^ b,
+                    })
+                    .unwrap_or(0);
+                let result = if *invert { !result } else { result };
+                vec![Some(result & all)]
+            }
+            Kind::Not => vec![Some(!value(0) & all)],
+            Kind::Multiplexer => {
+                let selected = 1 + value(0) as usize;
+                vec![if selected < self.inputs.len() {
+                    net(selected).map(|value| value & all)
+                } else {
+                    None
+                }]
+            }
+            Kind::Splitter { inputs, outputs } => {
+                let mut combined = 0u128;
+                let mut shift = 0;
+                for (i, bits) in inputs.iter().enumerate() {
+                    combined |= u128::from(value(i) & mask(*bits)) << shift;
+                    shift = (shift + bits).min(127);
+                }
+                let mut shift = 0;
+                outputs
+                    .iter()
+                    .map(|bits| {
+                        let part = (combined >> shift) as u64 & mask(*bits);
+                        shift = (shift + bits).min(127);
+                        Some(part)
+                    })
+                    .collect()
+            }
+            Kind::Driver => {
+                if value(1) & 1 == 1 {
+                    vec![net(0).map(|value| value & all)]
+                } else {
+                    vec![None]
+                }
+            }
+            Kind::DFlipFlop => {
+                let clock = value(1) & 1;
+                if clock == 1 && self.clock == 0 {
+                    self.state = Some(value(0) & all);
+                }
+                self.clock = clock;
+                let state = self.state.unwrap_or(0);
+                vec![Some(state), Some(!state & all)]
+            }
+            Kind::Add => {
+                let sum = u128::from(value(0) & all)
+                    + u128::from(value(1) & all)
+                    + u128::from(value(2) & 1);
+                vec![
+                    Some(sum as u64 & all),
+                    Some((sum >> self.bits.min(64)) as u64 & 1),
+                ]
+            }
+            Kind::Comparator { signed } => {
+                let (a, b) = (value(0) & all, value(1) & all);
+                let ordering = if *signed {
+                    sign_extend(a, self.bits).cmp(&sign_extend(b, self.bits))
+                } else {
+                    a.cmp(&b)
+                };
+                [ordering.is_gt(), ordering.is_eq(), ordering.is_lt()]
+                    .map(|result| Some(u64::from(result)))
+                    .to_vec()
+            }
+        }
+    }
+}
+
+fn sign_extend(value: u64, bits: u64) -> i64 {
+    if bits == 0 || bits >= 64 {
+        return value as i64;
+    }
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// A backend which runs the test on a [`Simulator`] instead of writing a test bench.
+/// Failed checks are reported like [`VcdCheckBackend`](crate::VcdCheckBackend) does.
+#[derive(Debug, Clone)]
+pub struct SimulationBackend {
+    simulator: Simulator,
+    checks: usize,
+    failures: usize,
+}
+
+impl SimulationBackend {
+    pub fn new(simulator: Simulator) -> Self {
+        Self {
+            simulator,
+            checks: 0,
+            failures: 0,
+        }
+    }
+
+    fn set_inputs<'s>(
+        &mut self,
+        inputs: impl IntoIterator<Item = (&'s Signal, InputValue)>,
+    ) -> miette::Result<()> {
+        for (signal, value) in inputs {
+            self.simulator.set_input(&signal.name, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl TestbenchBackend for SimulationBackend {
+    fn header(&mut self, _out: &mut dyn Write, _test_case: &TestCase) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied())
+    }
+
+    fn reset_step(
+        &mut self,
+        _out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().copied())?;
+        Ok(self.simulator.settle()?)
+    }
+
+    fn stimulus(
+        &mut self,
+        _out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        self.set_inputs(inputs.iter().map(|input| (input.signal, input.value)))
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        self.simulator.settle()?;
+        for output in expected {
+            let bits = output.signal.bits as usize;
+            let value = match output.value {
+                ExpectedValue::Value(value) => binary(value, bits),
+                ExpectedValue::Z => "z".repeat(bits),
+                ExpectedValue::X => continue,
+            };
+            let found = match self.simulator.output(&output.signal.name)? {
+                Some(found) => binary(found as i64, bits),
+                None => "z".repeat(bits),
+            };
+            if found != value {
+                outputln!(
+                    out,
+                    "ASSERTION FAILED on line {line}: {} is {found}, expected {value}",
+                    output.signal.name
+                )?;
+                self.failures += 1;
+            }
+            self.checks += 1;
+        }
+        Ok(())
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        if self.failures > 0 {
+            return Err(ChecksFailed {
+                failures: self.failures,
+                checks: self.checks,
+            }
+            .into());
+        }
+        outputln!(out, "All {} checks passed.", self.checks)
+    }
+}
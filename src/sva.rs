@@ -0,0 +1,325 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+use crate::verilog::{
+    binary, exported_identifier, verilog_string, width, VerilogIdentifier, VerilogValue,
+};
+use crate::TestbenchBackend;
+
+/// The name of the checker module
+const CHECKER_MODULE: &str = "tb_checker";
+
+/// The name of the package shared by the test bench and the checker
+const SYNC_PACKAGE: &str = "tb_sync";
+
+/// A backend which separates the stimulus from the checks. The test bench module `tb` only
+/// applies the inputs of each row. The expected outputs are checked by the module `tb_checker`
+/// with SystemVerilog concurrent assertions, and the checker is bound to the DUT with `bind`.
+/// The ports of the checker are connected to the ports of the DUT as they are named in the
+/// Verilog export of Digital.
+///
+/// The test bench and the checker share the package `tb_sync`. After the inputs of a row have
+/// settled, the test bench triggers the event `tb_sync::sample`, on which the checker samples
+/// the outputs of that row. Failed assertions are counted in `tb_sync::error_count`, and the
+/// test bench ends with `$fatal` if there were any. Another stimulus source can reuse the
+/// checker by triggering the event once per row in the same way. Since the outputs are sampled
+/// before the inputs change, the delay after setting the inputs must not be zero.
+#[derive(Debug, Clone)]
+pub struct SvaBackend {
+    module: String,
+    timescale: Option<String>,
+    delay: (u32, u32),
+    signals: Vec<Signal>,
+    rows: Vec<CheckerRow>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("The checker module needs a delay between setting the inputs and checking the outputs")]
+#[diagnostic(help("Use a delay such as \"10:0\""))]
+pub struct ZeroSamplingDelay;
+
+#[derive(Debug, Clone)]
+struct CheckerRow {
+    /// The source line, or `None` for a step of the reset sequence
+    line: Option<usize>,
+    expected: Vec<(usize, ExpectedValue)>,
+}
+
+impl SvaBackend {
+    /// Create a backend for testing the module `module`, which the checker is bound to
+    pub fn new(module: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            timescale: None,
+            delay: (10, 0),
+            signals: vec![],
+            rows: vec![],
+        }
+    }
+
+    pub fn with_timescale(mut self, timescale: impl Into<Option<String>>) -> Self {
+        self.timescale = timescale.into();
+        self
+    }
+
+    pub fn with_delay(mut self, delay: (u32, u32)) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn index_of(&self, signal: &Signal) -> Option<usize> {
+        self.signals.iter().position(|sig| sig.name == signal.name)
+    }
+
+    fn write_inputs<'s>(
+        &self,
+        out: &mut dyn Write,
+        inputs: impl IntoIterator<Item = (&'s Signal, InputValue)>,
+    ) -> miette::Result<()> {
+        for (signal, value) in inputs {
+            let identifier = VerilogIdentifier::from_input(signal);
+            let value = VerilogValue::from(value);
+            outputln!(out, "    {identifier} = {value};")?;
+        }
+        Ok(())
+    }
+
+    /// Trigger the sampling event of the checker for the row just written, and wait for the
+    /// rest of the row's time
+    fn sample(&self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(out, "-> {SYNC_PACKAGE}::sample;")?;
+        if self.delay.1 > 0 {
+            outputln!(out, "#{};", self.delay.1)?;
+        }
+        outputln!(out)
+    }
+
+    /// The signals checked by the test, with the index of each signal
+    fn checked_signals(&self) -> Vec<(usize, &Signal)> {
+        self.signals
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                self.rows
+                    .iter()
+                    .any(|row| row.expected.iter().any(|(j, _)| j == i))
+            })
+            .collect()
+    }
+
+    fn write_checker(&self, out: &mut dyn Write) -> miette::Result<()> {
+        let ports = self
+            .signals
+            .iter()
+            .map(|sig| format!("    input {}{}", width(sig), VerilogIdentifier::from(sig)))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        outputln!(out, "module {CHECKER_MODULE} (\n{ports}\n);")?;
+
+        let checked = self.checked_signals();
+        let count = self.rows.len().max(1);
+        outputln!(out, "localparam ROWS = {count};")?;
+        outputln!(out, "integer line [0:ROWS-1];")?;
+        for &(i, sig) in &checked {
+            outputln!(out, "reg check_{i} [0:ROWS-1];")?;
+            outputln!(out, "reg {}expected_{i} [0:ROWS-1];", width(sig))?;
+        }
+        outputln!(out, "integer row = 0;")?;
+        outputln!(out, "integer i;\n")?;
+
+        outputln!(out, "initial begin")?;
+        outputln!(out, "  for (i = 0; i < ROWS; i += 1) begin")?;
+        outputln!(out, "    line[i] = 0;")?;
+        for (i, _) in &checked {
+            outputln!(out, "    check_{i}[i] = 0;")?;
+        }
+        outputln!(out, "  end")?;
+        for (n, row) in self.rows.iter().enumerate() {
+            if let Some(line) = row.line {
+                outputln!(out, "  line[{n}] = {line};")?;
+            }
+            for (i, value) in &row.expected {
+                let bits = self.signals[*i].bits as usize;
+                let value = match value {
+                    ExpectedValue::Value(value) => binary(*value, bits),
+                    ExpectedValue::Z => "z".repeat(bits),
+                    ExpectedValue::X => continue,
+                };
+                outputln!(out, "  check_{i}[{n}] = 1;")?;
+                outputln!(out, "  expected_{i}[{n}] = {bits}'b{value};")?;
+            }
+        }
+        outputln!(out, "end\n")?;
+        outputln!(out, "always @({SYNC_PACKAGE}::sample) row += 1;\n")?;
+
+        for &(i, sig) in &checked {
+            let identifier = VerilogIdentifier::from(sig);
+            let message = verilog_string(&format!(
+                "ASSERTION FAILED on line %0d: {} is %b, expected %b",
+                sig.name.replace('%', "%%")
+            ));
+            outputln!(
+                out,
+                "assert property (@({SYNC_PACKAGE}::sample) check_{i}[row] |-> {identifier} === expected_{i}[row])"
+            )?;
+            outputln!(out, "  else begin")?;
+            outputln!(
+                out,
+                "    $error({message}, line[$sampled(row)], $sampled({identifier}), expected_{i}[$sampled(row)]);"
+            )?;
+            outputln!(out, "    {SYNC_PACKAGE}::error_count += 1;")?;
+            outputln!(out, "  end\n")?;
+        }
+        outputln!(out, "endmodule")
+    }
+}
+
+impl TestbenchBackend for SvaBackend {
+    fn header(&mut self, out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        if self.delay.0 == 0 {
+            return Err(ZeroSamplingDelay.into());
+        }
+        self.signals = test_case
+            .signals
+            .iter()
+            .filter(|sig| !matches!(sig.typ, SignalType::Virtual { .. }))
+            .cloned()
+            .collect();
+
+        if let Some(timescale) = &self.timescale {
+            outputln!(out, "`timescale {timescale}\n")?;
+        }
+
+        outputln!(out, "package {SYNC_PACKAGE};")?;
+        outputln!(out, "event sample;")?;
+        outputln!(out, "integer error_count = 0;")?;
+        outputln!(out, "endpackage\n")?;
+
+        let ports = self
+            .signals
+            .iter()
+            .map(|sig| {
+                let io_type = match sig.typ {
+                    SignalType::Input { .. } => "output reg",
+                    SignalType::Bidirectional { .. } => "inout",
+                    _ => "input",
+                };
+                format!(
+                    "    {io_type} {}{}",
+                    width(sig),
+                    VerilogIdentifier::from(sig)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        outputln!(out, "module tb (\n{ports}\n);")
+    }
+
+    fn signal_declarations(
+        &mut self,
+        out: &mut dyn Write,
+        signals: &[Signal],
+    ) -> miette::Result<()> {
+        for sig in signals.iter().filter(|sig| sig.is_bidirectional()) {
+            outputln!(
+                out,
+                "reg {}{} = {};",
+                width(sig),
+                VerilogIdentifier::from_input(sig),
+                VerilogValue::from(InputValue::Z)
+            )?;
+            outputln!(
+                out,
+                "assign {} = {};",
+                VerilogIdentifier::from(sig),
+                VerilogIdentifier::from_input(sig)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn begin(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(out, "initial begin")
+    }
+
+    fn initial_inputs(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.write_inputs(out, inputs.iter().copied())?;
+        outputln!(out)
+    }
+
+    fn reset_step(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.write_inputs(out, inputs.iter().copied())?;
+        outputln!(out, "#{};", self.delay.0)?;
+        self.rows.push(CheckerRow {
+            line: None,
+            expected: vec![],
+        });
+        self.sample(out)
+    }
+
+    fn stimulus(
+        &mut self,
+        out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        self.write_inputs(out, inputs.iter().map(|input| (input.signal, input.value)))?;
+        outputln!(out, "#{};", self.delay.0)
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        let expected = expected
+            .iter()
+            .filter_map(|output| Some((self.index_of(output.signal)?, output.value)))
+            .collect();
+        self.rows.push(CheckerRow {
+            line: Some(line),
+            expected,
+        });
+        self.sample(out)
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(out, "// Let the checker evaluate the last row")?;
+        outputln!(out, "#1;")?;
+        outputln!(out, "if ({SYNC_PACKAGE}::error_count > 0)")?;
+        outputln!(out, "    $fatal(1, \"There were failed assertions\");")?;
+        outputln!(out, "$display(\"All tests passed.\");")?;
+        outputln!(out, "end")?;
+        outputln!(out, "endmodule\n")?;
+        self.write_checker(out)?;
+        let connections = self
+            .signals
+            .iter()
+            .map(|sig| {
+                format!(
+                    "    .{}({})",
+                    VerilogIdentifier::from(sig),
+                    exported_identifier(&sig.name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        outputln!(
+            out,
+            "\nbind {} {CHECKER_MODULE} checker_inst (\n{connections}\n);",
+            VerilogIdentifier::from(&self.module)
+        )
+    }
+}
@@ -0,0 +1,252 @@
+use miette::{IntoDiagnostic, WrapErr};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The contents of a value change dump, eg, as written by `$dumpvars`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vcd {
+    pub timescale: Option<String>,
+    pub signals: Vec<VcdSignal>,
+    /// The time of the last time stamp in the dump
+    pub end_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcdSignal {
+    /// The hierarchical name, eg, `top.dut.A`
+    pub name: String,
+    pub width: usize,
+    /// The changes of the signal as time and value, with one character per bit and the most
+    /// significant bit first. Values are extended to the width of the signal.
+    pub changes: Vec<(u64, String)>,
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum VcdError {
+    #[error("Unexpected end of file in {0}")]
+    UnexpectedEof(&'static str),
+    #[error("Invalid {what} \"{token}\"")]
+    Invalid { what: &'static str, token: String },
+    #[error("Value change for undeclared identifier \"{0}\"")]
+    UndeclaredIdentifier(String),
+    #[error("No signal {name} found in the VCD file")]
+    NotFound {
+        name: String,
+        #[help]
+        available: String,
+    },
+    #[error("More than one signal {name} found in the VCD file")]
+    Ambiguous {
+        name: String,
+        #[help]
+        matching: String,
+    },
+}
+
+impl Vcd {
+    pub fn open(path: impl AsRef<Path>) -> miette::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Could not read {path:?}"))?;
+        Self::parse(&text).wrap_err_with(|| format!("Could not parse {path:?}"))
+    }
+
+    pub fn parse(text: &str) -> miette::Result<Self> {
+        Ok(Self::try_parse(text)?)
+    }
+
+    fn try_parse(text: &str) -> Result<Self, VcdError> {
+        let mut tokens = text.split_whitespace();
+        let mut timescale = None;
+        let mut scopes: Vec<&str> = vec![];
+        let mut signals: Vec<VcdSignal> = vec![];
+        let mut codes: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut time = 0;
+
+        while let Some(token) = tokens.next() {
+            if let Some(keyword) = token.strip_prefix('$') {
+                match keyword {
+                    "timescale" => {
+                        timescale = Some(until_end(&mut tokens, "$timescale")?.join(" "));
+                    }
+                    "scope" => {
+                        let arguments = until_end(&mut tokens, "$scope")?;
+                        let [_, name] = arguments[..] else {
+                            return Err(VcdError::Invalid {
+                                what: "scope",
+                                token: arguments.join(" "),
+                            });
+                        };
+                        scopes.push(name);
+                    }
+                    "upscope" => {
+                        until_end(&mut tokens, "$upscope")?;
+                        scopes.pop();
+                    }
+                    "var" => {
+                        let arguments = until_end(&mut tokens, "$var")?;
+                        let [_, width, code, reference, ..] = arguments[..] else {
+                            return Err(VcdError::Invalid {
+                                what: "variable",
+                                token: arguments.join(" "),
+                            });
+                        };
+                        let width = width.parse().map_err(|_| VcdError::Invalid {
+                            what: "width",
+                            token: width.to_string(),
+                        })?;
+                        let reference = reference.strip_prefix('\\').unwrap_or(reference);
+                        let name = scopes
+                            .iter()
+                            .copied()
+                            .chain(std::iter::once(reference))
+                            .collect::<Vec<_>>()
+                            .join(".");
+                        codes.entry(code).or_default().push(signals.len());
+                        signals.push(VcdSignal {
+                            name,
+                            width,
+                            changes: vec![],
+                        });
+                    }
+                    "comment" | "date" | "version" | "enddefinitions" => {
+                        until_end(&mut tokens, "header")?;
+                    }
+                    _ => {}
+                }
+            } else if let Some(t) = token.strip_prefix('#') {
+                time = t.parse().map_err(|_| VcdError::Invalid {
+                    what: "time",
+                    token: token.to_string(),
+                })?;
+            } else if let Some(bits) = token.strip_prefix(['b', 'B']) {
+                let code = tokens
+                    .next()
+                    .ok_or(VcdError::UnexpectedEof("value change"))?;
+                change(&mut signals, &codes, code, time, bits)?;
+            } else if token.starts_with(['r', 'R']) {
+                tokens.next();
+            } else if token.starts_with(['0', '1', 'x', 'X', 'z', 'Z']) {
+                let (bit, code) = token.split_at(1);
+                change(&mut signals, &codes, code, time, bit)?;
+            } else {
+                return Err(VcdError::Invalid {
+                    what: "token",
+                    token: token.to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            timescale,
+            signals,
+            end_time: time,
+        })
+    }
+
+    /// Find a signal by its hierarchical name, or by a suffix of it such as `A` or `dut.A`
+    pub fn find(&self, name: &str) -> Result<&VcdSignal, VcdError> {
+        if let Some(signal) = self.signals.iter().find(|signal| signal.name == name) {
+            return Ok(signal);
+        }
+        let suffix = format!(".{name}");
+        let matching = self
+            .signals
+            .iter()
+            .filter(|signal| signal.name.ends_with(&suffix))
+            .collect::<Vec<_>>();
+        match matching[..] {
+            [signal] => Ok(signal),
+            [] => Err(VcdError::NotFound {
+                name: name.to_string(),
+                available: format!("The available signals are: {}", names(self.signals.iter())),
+            }),
+            _ => Err(VcdError::Ambiguous {
+                name: name.to_string(),
+                matching: format!("The matching signals are: {}", names(matching)),
+            }),
+        }
+    }
+}
+
+impl VcdSignal {
+    /// The value of the signal at `time`, including changes at `time`
+    pub fn value_at(&self, time: u64) -> Option<&str> {
+        let i = self.changes.partition_point(|(t, _)| *t <= time);
+        i.checked_sub(1).map(|i| self.changes[i].1.as_str())
+    }
+
+    /// The value of the signal just before `time`, excluding changes at `time`
+    pub fn value_before(&self, time: u64) -> Option<&str> {
+        let i = self.changes.partition_point(|(t, _)| *t < time);
+        i.checked_sub(1).map(|i| self.changes[i].1.as_str())
+    }
+
+    /// The times at which the signal changes from 0 to 1
+    pub fn rising_edges(&self) -> Vec<u64> {
+        self.changes
+            .windows(2)
+            .filter(|pair| pair[0].1 == "0" && pair[1].1 == "1")
+            .map(|pair| pair[1].0)
+            .collect()
+    }
+}
+
+fn names<'a>(signals: impl IntoIterator<Item = &'a VcdSignal>) -> String {
+    signals
+        .into_iter()
+        .map(|signal| signal.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn until_end<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    command: &'static str,
+) -> Result<Vec<&'a str>, VcdError> {
+    let mut arguments = vec![];
+    for token in tokens {
+        if token == "$end" {
+            return Ok(arguments);
+        }
+        arguments.push(token);
+    }
+    Err(VcdError::UnexpectedEof(command))
+}
+
+fn change(
+    signals: &mut [VcdSignal],
+    codes: &HashMap<&str, Vec<usize>>,
+    code: &str,
+    time: u64,
+    bits: &str,
+) -> Result<(), VcdError> {
+    let Some(indices) = codes.get(code) else {
+        return Err(VcdError::UndeclaredIdentifier(code.to_string()));
+    };
+    for &i in indices {
+        let signal = &mut signals[i];
+        let bits = extend(&bits.to_ascii_lowercase(), signal.width);
+        match signal.changes.last_mut() {
+            Some((t, value)) if *t == time => *value = bits,
+            Some((_, value)) if *value == bits => {}
+            _ => signal.changes.push((time, bits)),
+        }
+    }
+    Ok(())
+}
+
+/// Extend a value to `width` bits. Values starting with `x` or `z` are extended with that bit,
+/// all others with `0`.
+fn extend(bits: &str, width: usize) -> String {
+    if bits.len() >= width {
+        return bits.to_string();
+    }
+    let fill = if bits.starts_with(['x', 'z']) {
+        &bits[..1]
+    } else {
+        "0"
+    };
+    fill.repeat(width - bits.len()) + bits
+}
@@ -1,4 +1,10 @@
-use digital_test_runner::{ExpectedValue, InputValue, Signal};
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+use crate::{Port, Section, TestbenchBackend};
 
 const REG_SUFFIX: &str = "_reg";
 
@@ -86,6 +92,20 @@ impl std::fmt::Display for VerilogValue {
     }
 }
 
+/// The mask selecting the lowest `bits` bits of a value
+pub(crate) fn mask(bits: u64) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// The lowest `bits` bits of `value` as binary digits, most significant bit first
+pub(crate) fn binary(value: i64, bits: usize) -> String {
+    format!("{:0bits$b}", value as u64 & mask(bits as u64))
+}
+
 impl<'a> std::fmt::Display for VerilogIdentifier<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         static RE: once_cell::sync::Lazy<regex::Regex> =
@@ -98,3 +118,463 @@ impl<'a> std::fmt::Display for VerilogIdentifier<'a> {
         }
     }
 }
+
+/// The port of a module exported by Digital for the label `label`. Digital drops the bars of
+/// labels such as `|S|`, and escapes the other labels which are not valid identifiers.
+pub(crate) fn exported_identifier(label: &str) -> String {
+    VerilogIdentifier::from(label.replace('|', "").as_str()).to_string()
+}
+
+pub(crate) fn width(sig: &Signal) -> String {
+    if sig.bits > 1 {
+        format!("[{}:0] ", sig.bits - 1)
+    } else {
+        String::from("")
+    }
+}
+
+/// Write an empty module `module` with the ports of the test case, as a starting point for the
+/// DUT. Outputs are tied to `'x` and bidirectional signals are left undriven.
+pub fn write_stub(test_case: &TestCase, module: &str, out: &mut dyn Write) -> miette::Result<()> {
+    let ports = test_case
+        .signals
+        .iter()
+        .filter_map(|sig| {
+            let io_type = match sig.typ {
+                SignalType::Input { .. } => "input",
+                SignalType::Output => "output",
+                SignalType::Bidirectional { .. } => "inout",
+                SignalType::Virtual { .. } => return None,
+            };
+            Some(format!(
+                "  {io_type} {}{}",
+                width(sig),
+                VerilogIdentifier::from(sig)
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    outputln!(
+        out,
+        "module {} (\n{ports}\n);",
+        VerilogIdentifier::from(module)
+    )?;
+    for sig in &test_case.signals {
+        if matches!(sig.typ, SignalType::Output) {
+            outputln!(out, "  assign {} = 'x;", VerilogIdentifier::from(sig))?;
+        }
+    }
+    outputln!(out, "endmodule")
+}
+
+/// Write a module `top` which connects an instance of `dut_module` to the test bench `tb`.
+/// The DUT is connected by position, since the modules exported by Digital rename ports whose
+/// label is not a valid identifier. Ports of the DUT which are not part of the test are left
+/// unconnected.
+pub(crate) fn write_top_module(
+    test_case: &TestCase,
+    dut_ports: &[Port],
+    dut_module: &str,
+    out: &mut dyn Write,
+) -> miette::Result<()> {
+    outputln!(out, "module top;")?;
+    for sig in &test_case.signals {
+        outputln!(
+            out,
+            "  wire {}{};",
+            width(sig),
+            VerilogIdentifier::from(sig)
+        )?;
+    }
+    let unconnected = dut_ports
+        .iter()
+        .filter(|port| !test_case.signals.iter().any(|sig| sig.name == port.name))
+        .collect::<Vec<_>>();
+    for (i, port) in unconnected.iter().enumerate() {
+        let width = if port.bits > 1 {
+            format!("[{}:0] ", port.bits - 1)
+        } else {
+            String::new()
+        };
+        outputln!(out, "  wire {width}unconnected_{i};")?;
+    }
+    outputln!(out)?;
+
+    let dut_ports = dut_ports
+        .iter()
+        .map(|port| {
+            match unconnected
+                .iter()
+                .position(|unconnected| unconnected.name == port.name)
+            {
+                Some(i) => format!("      unconnected_{i}"),
+                None => format!("      {}", VerilogIdentifier::from(&port.name)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    outputln!(
+        out,
+        "  {} dut (\n{dut_ports}\n  );",
+        VerilogIdentifier::from(dut_module)
+    )?;
+
+    let tb_ports = test_case
+        .signals
+        .iter()
+        .map(|sig| format!("      {}", VerilogIdentifier::from(sig)))
+        .collect::<Vec<_>>()
+        .join(",\n");
+    outputln!(out, "  tb tb (\n{tb_ports}\n  );")?;
+    outputln!(out, "endmodule")
+}
+
+/// The built-in backend, which generates a Verilog test bench module `tb` with one port for each signal
+#[derive(Debug, Clone)]
+pub struct VerilogBackend {
+    timescale: Option<String>,
+    delay: (u32, u32),
+    dump: Option<WaveformDump>,
+    max_errors: Option<usize>,
+    sections: Vec<String>,
+}
+
+/// Waveform dumping with `$dumpfile` and `$dumpvars`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveformDump {
+    /// The file to dump to. Icarus Verilog writes an FST file instead of a VCD file if the
+    /// simulation is run with `vvp -fst`.
+    pub file: String,
+    pub scope: DumpScope,
+    /// Stop dumping the given number of rows after the row with the first failed assertion. The
+    /// dump then holds the rows leading up to the failure and the rows following it, but not the
+    /// rest of a long test.
+    pub after_failure: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DumpScope {
+    /// Only the ports of the test bench
+    Ports,
+    /// The full design hierarchy, including the DUT
+    #[default]
+    All,
+}
+
+impl WaveformDump {
+    pub fn new(file: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            scope: DumpScope::default(),
+            after_failure: None,
+        }
+    }
+}
+
+pub(crate) fn verilog_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quote `s` for use as a `$display` format string
+fn display_string(s: &str) -> String {
+    verilog_string(&s.replace('%', "%%"))
+}
+
+impl Default for VerilogBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VerilogBackend {
+    pub fn new() -> Self {
+        Self {
+            timescale: None,
+            delay: (0, 10),
+            dump: None,
+            max_errors: None,
+            sections: vec![],
+        }
+    }
+
+    pub fn with_timescale(mut self, timescale: impl Into<Option<String>>) -> Self {
+        self.timescale = timescale.into();
+        self
+    }
+
+    pub fn with_delay(mut self, delay: (u32, u32)) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_dump(mut self, dump: impl Into<Option<WaveformDump>>) -> Self {
+        self.dump = dump.into();
+        self
+    }
+
+    /// Stop the simulation with `$finish_and_return(1)` once `max_errors` assertions have failed
+    pub fn with_max_errors(mut self, max_errors: impl Into<Option<usize>>) -> Self {
+        self.max_errors = max_errors.into();
+        self
+    }
+
+    fn assert_macro(&self) -> String {
+        let mut body = vec![];
+        if !self.sections.is_empty() {
+            body.push(String::from(
+                "if (section >= 0) section_checks[section] += 1;",
+            ));
+        }
+        body.push(String::from("if (signal !== value) begin"));
+        body.push(String::from(
+            "    $display(\"ASSERTION FAILED on line line_num: signal != value\");",
+        ));
+        body.push(String::from("    error_count += 1;"));
+        if !self.sections.is_empty() {
+            body.push(String::from(
+                "    if (section >= 0) section_errors[section] += 1;",
+            ));
+        }
+        if self
+            .dump
+            .as_ref()
+            .is_some_and(|dump| dump.after_failure.is_some())
+        {
+            body.push(String::from("    if (error_count == 1) -> dump_trigger;"));
+        }
+        if let Some(max_errors) = self.max_errors {
+            let message = if max_errors == 1 {
+                String::from("Stopping at the first failed assertion")
+            } else {
+                format!("Stopping after {max_errors} failed assertions")
+            };
+            body.push(format!("    if (error_count >= {max_errors}) begin"));
+            body.push(format!("        $display(\"{message}\");"));
+            body.push(String::from("        $finish_and_return(1);"));
+            body.push(String::from("    end"));
+        }
+        body.push(String::from("end"));
+
+        let mut s = String::from("`define assert_eq(line_num, signal, value)");
+        for line in body {
+            s.push_str(" \\\n    ");
+            s.push_str(&line);
+        }
+        s
+    }
+
+    fn dump_declarations(&self, out: &mut dyn Write) -> miette::Result<()> {
+        let Some(dump) = &self.dump else {
+            return Ok(());
+        };
+
+        if dump.after_failure.is_some() {
+            outputln!(out, "event dump_trigger;")?;
+        }
+        outputln!(out, "initial begin")?;
+        outputln!(out, "  $dumpfile({});", verilog_string(&dump.file))?;
+        match dump.scope {
+            DumpScope::Ports => outputln!(out, "  $dumpvars(1, tb);")?,
+            DumpScope::All => outputln!(out, "  $dumpvars;")?,
+        }
+        if let Some(rows) = dump.after_failure {
+            let row_time = u64::from(self.delay.0) + u64::from(self.delay.1);
+            outputln!(out, "  @(dump_trigger);")?;
+            outputln!(out, "  #{};", rows as u64 * row_time)?;
+            outputln!(out, "  $dumpoff;")?;
+        }
+        outputln!(out, "end")
+    }
+}
+
+impl TestbenchBackend for VerilogBackend {
+    fn header(&mut self, out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        if let Some(timescale) = &self.timescale {
+            outputln!(out, "`timescale {timescale}\n")?;
+        }
+
+        outputln!(out, "{}", self.assert_macro())?;
+        outputln!(out)?;
+
+        let ports = test_case
+            .signals
+            .iter()
+            .map(|sig| {
+                let io_type = match sig.typ {
+                    SignalType::Input { .. } => "output reg",
+                    SignalType::Output => "input",
+                    SignalType::Bidirectional { .. } => "inout",
+                    SignalType::Virtual { .. } => unreachable!(),
+                };
+                format!(
+                    "    {io_type} {}{}",
+                    width(sig),
+                    VerilogIdentifier::from(sig)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        outputln!(out, "module tb (\n{ports}\n);")?;
+        Ok(())
+    }
+
+    fn signal_declarations(
+        &mut self,
+        out: &mut dyn Write,
+        signals: &[Signal],
+    ) -> miette::Result<()> {
+        outputln!(out, "integer error_count = 0;")?;
+        if !self.sections.is_empty() {
+            let last = self.sections.len() - 1;
+            outputln!(out, "integer section = -1;")?;
+            outputln!(out, "integer section_checks [0:{last}];")?;
+            outputln!(out, "integer section_errors [0:{last}];")?;
+        }
+
+        for sig in signals {
+            if sig.is_bidirectional() {
+                outputln!(
+                    out,
+                    "reg {} = {};",
+                    VerilogIdentifier::from_input(sig),
+                    VerilogValue::from(InputValue::Z)
+                )?;
+            }
+        }
+
+        for sig in signals {
+            if sig.is_bidirectional() {
+                outputln!(
+                    out,
+                    "assign {} = {};",
+                    VerilogIdentifier::from(sig),
+                    VerilogIdentifier::from_input(sig)
+                )?;
+            }
+        }
+
+        self.dump_declarations(out)
+    }
+
+    fn set_sections(&mut self, sections: &[Section]) -> miette::Result<()> {
+        self.sections = sections
+            .iter()
+            .map(|section| section.name.clone())
+            .collect();
+        Ok(())
+    }
+
+    fn begin(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(out, "initial begin")?;
+        if !self.sections.is_empty() {
+            let count = self.sections.len();
+            outputln!(
+                out,
+                "  for (section = 0; section < {count}; section += 1) begin"
+            )?;
+            outputln!(out, "    section_checks[section] = 0;")?;
+            outputln!(out, "    section_errors[section] = 0;")?;
+            outputln!(out, "  end")?;
+            outputln!(out, "  section = -1;\n")?;
+        }
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        for (signal, value) in inputs {
+            let identifier = VerilogIdentifier::from_input(signal);
+            let value = VerilogValue::from(*value);
+            outputln!(out, "    {identifier} = {value};")?;
+        }
+        outputln!(out)
+    }
+
+    fn reset_step(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        for (signal, value) in inputs {
+            let identifier = VerilogIdentifier::from_input(signal);
+            let value = VerilogValue::from(*value);
+            outputln!(out, "    {identifier} = {value};")?;
+        }
+        outputln!(out, "#{};", self.delay.0)?;
+        if self.delay.1 > 0 {
+            outputln!(out, "#{};", self.delay.1)?;
+        }
+        outputln!(out)
+    }
+
+    fn section(
+        &mut self,
+        out: &mut dyn Write,
+        index: usize,
+        section: &Section,
+    ) -> miette::Result<()> {
+        outputln!(out, "    section = {index};")?;
+        outputln!(
+            out,
+            "    $display({});\n",
+            display_string(&format!("Section {}", section.name))
+        )
+    }
+
+    fn stimulus(
+        &mut self,
+        out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        for input in inputs {
+            let identifier = VerilogIdentifier::from_input(input.signal);
+            let value = VerilogValue::from(input.value);
+            outputln!(out, "    {identifier} = {value};")?;
+        }
+        outputln!(out, "#{};", self.delay.0)
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        for output in expected {
+            let identifier = VerilogIdentifier::from(output.signal);
+            let value = VerilogValue::from(output.value);
+            outputln!(out, "    `assert_eq({line}, {identifier}, {value});")?;
+        }
+        if self.delay.1 > 0 {
+            outputln!(out, "#{};", self.delay.1)?;
+        }
+        outputln!(out)
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        for (i, name) in self.sections.iter().enumerate() {
+            outputln!(
+                out,
+                "  $display({}, section_errors[{i}], section_checks[{i}]);",
+                verilog_string(&format!(
+                    "{}: %0d of %0d checks failed",
+                    name.replace('%', "%%")
+                ))
+            )?;
+        }
+        outputln!(out, "  if(error_count > 0) begin")?;
+        outputln!(out, "    $display(\"There were failed assertions\");")?;
+        outputln!(out, "    $finish_and_return(1);")?;
+        outputln!(out, "  end")?;
+        outputln!(out, "  $display(\"All tests passed.\");")?;
+
+        outputln!(out, "end")?;
+        outputln!(out, "endmodule")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use digital_test_runner::{
+    ExpectedEntry, ExpectedValue, InputEntry, InputValue, Signal, SignalType, TestCase,
+};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+use crate::verilog::binary;
+use crate::TestbenchBackend;
+
+/// A backend which writes a VCD file with the stimulus and the expected outputs of the test,
+/// using the same timing as the Verilog test bench. Outputs change to their expected value when
+/// they are checked and are `x` when the test does not care about their value.
+#[derive(Debug, Clone)]
+pub struct VcdBackend {
+    timescale: String,
+    delay: (u32, u32),
+    signals: Vec<(String, u64)>,
+    outputs: Vec<usize>,
+    values: Vec<String>,
+    time: u64,
+    written_time: Option<u64>,
+}
+
+impl Default for VcdBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VcdBackend {
+    pub fn new() -> Self {
+        Self {
+            timescale: String::from("1s"),
+            delay: (0, 10),
+            signals: vec![],
+            outputs: vec![],
+            values: vec![],
+            time: 0,
+            written_time: None,
+        }
+    }
+
+    /// Use the time unit of a Verilog timescale such as `10ns/1ns`.
+    /// Without a timescale the Verilog default of `1s` is used.
+    pub fn with_timescale(mut self, timescale: impl Into<Option<String>>) -> Self {
+        if let Some(timescale) = timescale.into() {
+            let unit = timescale.split('/').next().unwrap_or_default();
+            self.timescale = unit.trim().to_string();
+        }
+        self
+    }
+
+    pub fn with_delay(mut self, delay: (u32, u32)) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn index_of(&self, signal: &Signal) -> Option<usize> {
+        self.signals
+            .iter()
+            .position(|(name, _)| *name == signal.name)
+    }
+
+    fn format(&self, index: usize, bits: &str) -> String {
+        let code = identifier_code(index);
+        if self.signals[index].1 > 1 {
+            format!("b{bits} {code}")
+        } else {
+            format!("{bits}{code}")
+        }
+    }
+
+    /// Write the changes at the current time, skipping values which did not change
+    fn write_changes(
+        &mut self,
+        out: &mut dyn Write,
+        changes: impl IntoIterator<Item = (usize, String)>,
+    ) -> miette::Result<()> {
+        for (index, bits) in changes {
+            if self.values[index] == bits {
+                continue;
+            }
+            if self.written_time != Some(self.time) {
+                outputln!(out, "#{}", self.time)?;
+                self.written_time = Some(self.time);
+            }
+            outputln!(out, "{}", self.format(index, &bits))?;
+            self.values[index] = bits;
+        }
+        Ok(())
+    }
+
+    fn input_changes<'s>(
+        &'s self,
+        inputs: impl IntoIterator<Item = (&'s Signal, InputValue)> + 's,
+    ) -> impl Iterator<Item = (usize, String)> + 's {
+        inputs.into_iter().filter_map(|(signal, value)| {
+            let index = self.index_of(signal)?;
+            let bits = self.signals[index].1 as usize;
+            Some((index, input_bits(value, bits)))
+        })
+    }
+}
+
+/// The identifier code of the signal with the given index, using the printable characters
+fn identifier_code(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+    }
+}
+
+fn input_bits(value: InputValue, bits: usize) -> String {
+    match value {
+        InputValue::Value(value) => binary(value, bits),
+        InputValue::Z => "z".repeat(bits),
+    }
+}
+
+fn expected_bits(value: ExpectedValue, bits: usize) -> String {
+    match value {
+        ExpectedValue::Value(value) => binary(value, bits),
+        ExpectedValue::Z => "z".repeat(bits),
+        ExpectedValue::X => "x".repeat(bits),
+    }
+}
+
+impl TestbenchBackend for VcdBackend {
+    fn header(&mut self, out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        self.signals = test_case
+            .signals
+            .iter()
+            .filter(|signal| !matches!(signal.typ, SignalType::Virtual { .. }))
+            .map(|signal| (signal.name.clone(), signal.bits))
+            .collect();
+        self.outputs = test_case
+            .signals
+            .iter()
+            .filter(|signal| !matches!(signal.typ, SignalType::Virtual { .. }))
+            .enumerate()
+            .filter(|(_, signal)| matches!(signal.typ, SignalType::Output))
+            .map(|(i, _)| i)
+            .collect();
+        self.values = self
+            .signals
+            .iter()
+            .map(|(_, bits)| "x".repeat(*bits as usize))
+            .collect();
+
+        outputln!(out, "$timescale {} $end", self.timescale)?;
+        outputln!(out, "$scope module expected $end")?;
+        for (i, (name, bits)) in self.signals.iter().enumerate() {
+            let name = name.replace(char::is_whitespace, "_");
+            outputln!(out, "$var wire {bits} {} {name} $end", identifier_code(i))?;
+        }
+        outputln!(out, "$upscope $end")?;
+        outputln!(out, "$enddefinitions $end")
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn begin(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        outputln!(out, "#0")?;
+        outputln!(out, "$dumpvars")?;
+        for (i, bits) in self.values.iter().enumerate() {
+            outputln!(out, "{}", self.format(i, bits))?;
+        }
+        outputln!(out, "$end")?;
+        self.written_time = Some(0);
+        Ok(())
+    }
+
+    fn initial_inputs(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        let changes = self
+            .input_changes(inputs.iter().copied())
+            .collect::<Vec<_>>();
+        self.write_changes(out, changes)
+    }
+
+    fn reset_step(
+        &mut self,
+        out: &mut dyn Write,
+        inputs: &[(&Signal, InputValue)],
+    ) -> miette::Result<()> {
+        self.initial_inputs(out, inputs)?;
+        self.time += u64::from(self.delay.0) + u64::from(self.delay.1);
+        Ok(())
+    }
+
+    fn stimulus(
+        &mut self,
+        out: &mut dyn Write,
+        _line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        let changes = self
+            .input_changes(inputs.iter().map(|input| (input.signal, input.value)))
+            .collect::<Vec<_>>();
+        self.write_changes(out, changes)?;
+        self.time += u64::from(self.delay.0);
+        Ok(())
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        _line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        let mut changes = self
+            .outputs
+            .iter()
+            .map(|&i| (i, "x".repeat(self.signals[i].1 as usize)))
+            .collect::<Vec<_>>();
+        for output in expected {
+            if let Some(index) = self.index_of(output.signal) {
+                let bits = self.signals[index].1 as usize;
+                let value = expected_bits(output.value, bits);
+                match changes.iter_mut().find(|(i, _)| *i == index) {
+                    Some((_, bits)) => *bits = value,
+                    None => changes.push((index, value)),
+                }
+            }
+        }
+        self.write_changes(out, changes)?;
+        self.time += u64::from(self.delay.1);
+        Ok(())
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        if self.written_time != Some(self.time) {
+            outputln!(out, "#{}", self.time)?;
+        }
+        Ok(())
+    }
+}
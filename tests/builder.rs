@@ -0,0 +1,137 @@
+use digital_test_runner::{dig, ExpectedEntry, InputEntry, Signal, TestCase};
+use digital_test_to_verilog::{Builder, DigitalBackend, InitialInputs, TestbenchBackend};
+use miette::IntoDiagnostic;
+use std::io::Write;
+
+mod util;
+
+fn load_adder_test() -> TestCase {
+    let dig_file = dig::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"))
+        .expect("Could not open adder.dig");
+    dig_file.load_test(0).expect("Could not load test case")
+}
+
+#[test]
+fn write_to_and_to_string_agree() {
+    let test_case = load_adder_test();
+
+    let mut out = Vec::new();
+    Builder::try_new(&test_case)
+        .unwrap()
+        .write_to(&mut out)
+        .unwrap();
+
+    let s = Builder::try_new(&test_case).unwrap().to_string().unwrap();
+
+    assert_eq!(String::from_utf8(out).unwrap(), s);
+    assert!(s.contains("module tb ("));
+}
+
+#[test]
+fn done_reports_io_errors() {
+    let dir = util::TempDir::create("done_reports_io_errors");
+    let test_case = load_adder_test();
+
+    let path = dir.file("missing_dir").join("out.v");
+    let io_error = std::fs::File::create(&path).unwrap_err().to_string();
+
+    let err = Builder::try_new(&test_case)
+        .unwrap()
+        .with_output(path)
+        .done()
+        .unwrap_err();
+
+    assert!(err.chain().any(|cause| cause.to_string() == io_error));
+
+    dir.delete();
+}
+
+struct CsvBackend;
+
+impl TestbenchBackend for CsvBackend {
+    fn header(&mut self, out: &mut dyn Write, test_case: &TestCase) -> miette::Result<()> {
+        let names = test_case
+            .signals
+            .iter()
+            .map(|sig| sig.name.as_str())
+            .collect::<Vec<_>>();
+        writeln!(out, "# {}", names.join(",")).into_diagnostic()
+    }
+
+    fn signal_declarations(
+        &mut self,
+        _out: &mut dyn Write,
+        _signals: &[Signal],
+    ) -> miette::Result<()> {
+        Ok(())
+    }
+
+    fn stimulus(
+        &mut self,
+        out: &mut dyn Write,
+        line: usize,
+        inputs: &[&InputEntry<'_>],
+    ) -> miette::Result<()> {
+        for input in inputs {
+            write!(out, "{line},{}={:?};", input.signal.name, input.value).into_diagnostic()?;
+        }
+        Ok(())
+    }
+
+    fn check(
+        &mut self,
+        out: &mut dyn Write,
+        _line: usize,
+        expected: &[&ExpectedEntry<'_>],
+    ) -> miette::Result<()> {
+        for output in expected {
+            write!(out, "{}=={:?};", output.signal.name, output.value).into_diagnostic()?;
+        }
+        writeln!(out).into_diagnostic()
+    }
+
+    fn footer(&mut self, out: &mut dyn Write) -> miette::Result<()> {
+        writeln!(out, "# end").into_diagnostic()
+    }
+}
+
+#[test]
+fn custom_backend_is_used() {
+    let test_case = load_adder_test();
+
+    let s = Builder::try_new(&test_case)
+        .unwrap()
+        .with_backend(CsvBackend)
+        .to_string()
+        .unwrap();
+
+    assert_eq!(
+        s,
+        "# A,B,|S|,C\n2,A=Value(1);2,B=Value(1);|S|==Value(2);\n# end\n"
+    );
+}
+
+#[test]
+fn digital_backend_rejects_initial_inputs_and_reset() {
+    let test_case = load_adder_test();
+
+    let err = Builder::try_new(&test_case)
+        .unwrap()
+        .with_backend(DigitalBackend::new("adder"))
+        .with_initial_inputs(InitialInputs::Zero)
+        .to_string()
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Initial inputs and input defaults are not supported"));
+
+    let err = Builder::try_new(&test_case)
+        .unwrap()
+        .with_backend(DigitalBackend::new("adder"))
+        .with_reset(vec!["A=0".parse().unwrap()])
+        .to_string()
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Reset sequences are not supported"));
+}
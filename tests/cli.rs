@@ -1,4 +1,5 @@
 use assert_cmd::Command;
+use predicates::prelude::*;
 
 mod util;
 
@@ -172,3 +173,1412 @@ fn output_to_file_works() {
 
     dir.delete();
 }
+
+#[test]
+fn can_load_test_by_glob() {
+    let expected_output = expected_output("", "#10;", "");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "glob:Sim*",
+    ])
+    .assert()
+    .success()
+    .stdout(expected_output);
+}
+
+#[test]
+fn can_load_test_by_regex() {
+    let expected_output = expected_output("", "#10;", "");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "re:^S.*e$",
+    ])
+    .assert()
+    .success()
+    .stdout(expected_output);
+}
+
+#[test]
+fn ambiguous_regex_lists_matching_tests() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "re:i",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("0: Simple"))
+    .stderr(predicates::str::contains("1: Failing"));
+}
+
+#[test]
+fn ambiguous_name_lists_matching_tests() {
+    let dir = util::TempDir::create("ambiguous_name_lists_matching_tests");
+    let file = dir.file("adder.dig");
+    let circuit =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"))
+            .unwrap()
+            .replace("<string>Failing</string>", "<string>Simple</string>");
+    std::fs::write(&file, circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file)
+        .arg("Simple")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "More than one test case \"Simple\" found",
+        ))
+        .stderr(predicates::str::contains("0: Simple"))
+        .stderr(predicates::str::contains("1: Simple"));
+    dir.delete();
+}
+
+#[test]
+fn missing_test_lists_available_tests() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "Missing",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("No test case \"Missing\" found"))
+    .stderr(predicates::str::contains("1: Failing"));
+}
+
+#[test]
+fn list_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "list",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+    ])
+    .assert()
+    .success()
+    .stdout(
+        r#"0: Simple (static, 1 row)
+    input   A [8]
+    input   B [8]
+    output  |S| [8]
+    output  C
+1: Failing (static, 1 row)
+    input   A [8]
+    input   B [8]
+    output  |S| [8]
+    output  C
+"#,
+    );
+}
+
+#[test]
+fn list_as_json_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let output = cmd
+        .args([
+            "list",
+            "--json",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["kind"], "static");
+    assert_eq!(json[0]["rows"], 1200);
+    assert_eq!(json[0]["signals"][0]["direction"], "input");
+}
+
+#[test]
+fn coverage_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "coverage",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        r#"1 row
+    input   A [8]: 1 of 256 values, 0 of 8 bits toggled
+    input   B [8]: 1 of 256 values, 0 of 8 bits toggled
+    output  |S| [8]: checked in 1 row, 1 of 256 values
+    output  C: never checked
+Never checked: C
+"#,
+    );
+}
+
+#[test]
+fn coverage_as_json_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    let output = cmd
+        .args([
+            "coverage",
+            "--json",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["rows"], 1200);
+    assert_eq!(json["inputs"][0]["name"], "CLK");
+    assert_eq!(json["inputs"][0]["values"], serde_json::json!([0, 1]));
+    assert_eq!(
+        json["inputs"][0]["toggles"][0],
+        serde_json::json!({"rose": true, "fell": true})
+    );
+    assert_eq!(json["outputs"][0]["name"], "QD");
+    assert!(json["outputs"][0]["checks"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn batch_works() {
+    let dir = util::TempDir::create("batch_works");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "adder*",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    let content = std::fs::read_to_string(dir.file("adder__0_Simple.v"))
+        .expect("Could not read output file.");
+    assert_eq!(content, expected_output("", "#10;", ""));
+    assert!(dir.file("adder__1_Failing.v").exists());
+    assert!(!dir.file("74162__0_unnamed.v").exists());
+
+    let manifest =
+        std::fs::read_to_string(dir.file("manifest.json")).expect("Could not read manifest.");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["generated"].as_array().unwrap().len(), 2);
+    assert_eq!(manifest["generated"][1]["output"], "adder__1_Failing.v");
+    assert_eq!(manifest["skipped"].as_array().unwrap().len(), 0);
+
+    let selected = dir.file("selected");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "adder*",
+        "--test",
+        "Simple",
+        "-o",
+    ])
+    .arg(&selected)
+    .assert()
+    .success();
+
+    let manifest =
+        std::fs::read_to_string(selected.join("manifest.json")).expect("Could not read manifest.");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["generated"].as_array().unwrap().len(), 1);
+    assert_eq!(manifest["skipped"][0]["name"], "Failing");
+    assert_eq!(
+        manifest["skipped"][0]["reason"],
+        "Not matched by the test case selector \"Simple\""
+    );
+
+    dir.delete();
+}
+
+#[test]
+fn batch_writes_makefile() {
+    let dir = util::TempDir::create("batch_writes_makefile");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "adder*",
+        "--makefile",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    let makefile = std::fs::read_to_string(dir.file("Makefile")).expect("Could not read Makefile.");
+    assert!(makefile.contains(
+        "adder__0_Simple.vvp: $(SRC_DIR)/adder.v adder__0_Simple.v adder__0_Simple_top.v $(SOURCES)"
+    ));
+    assert!(makefile.contains("check: all"));
+
+    let top = std::fs::read_to_string(dir.file("adder__0_Simple_top.v"))
+        .expect("Could not read top module.");
+    assert!(top.contains("  adder dut (\n      A,\n      B,\n      \\|S| ,\n      C\n  );"));
+
+    dir.delete();
+}
+
+#[test]
+fn batch_generates_embedded_test_cases() {
+    let dir = util::TempDir::create("batch_generates_embedded_test_cases");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "sum3.dig",
+        "--include",
+        "74779.dig",
+        "--hierarchy",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    assert!(dir.file("sum3__0_Sum.v").exists());
+    assert!(dir.file("sum3/adder__0_Simple.v").exists());
+    assert!(dir.file("sum3/adder__1_Failing.v").exists());
+
+    let manifest =
+        std::fs::read_to_string(dir.file("manifest.json")).expect("Could not read manifest.");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["generated"].as_array().unwrap().len(), 3);
+    assert_eq!(manifest["generated"][1]["dig"], "adder.dig");
+    assert_eq!(manifest["generated"][1]["output"], "sum3/adder__0_Simple.v");
+    assert_eq!(manifest["skipped"][0]["dig"], "74779.dig");
+    assert!(manifest["skipped"][0]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("Could not find the embedded circuit 74779-inc.dig"));
+
+    dir.delete();
+}
+
+#[test]
+fn batch_writes_makefile_for_embedded_circuits() {
+    let dir = util::TempDir::create("batch_writes_makefile_for_embedded_circuits");
+    let input = dir.file("input");
+    std::fs::create_dir(&input).unwrap();
+    let read = |name| {
+        std::fs::read_to_string(format!("{}/tests/data/{name}", env!("CARGO_MANIFEST_DIR")))
+            .unwrap()
+    };
+    std::fs::write(
+        input.join("sum3.dig"),
+        read("sum3.dig").replace("adder.dig", "my-adder.dig"),
+    )
+    .unwrap();
+    std::fs::write(input.join("my-adder.dig"), read("adder.dig")).unwrap();
+
+    let output = dir.file("output");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("batch")
+        .arg(&input)
+        .args(["--include", "sum3.dig", "--hierarchy", "--makefile", "-o"])
+        .arg(&output)
+        .assert()
+        .success();
+
+    let makefile =
+        std::fs::read_to_string(output.join("Makefile")).expect("Could not read Makefile.");
+    assert!(makefile.contains(
+        "sum3/my-adder__0_Simple.vvp: $(SRC_DIR)/sum3.v sum3/my-adder__0_Simple.v sum3/my-adder__0_Simple_top.v $(SOURCES)\n"
+    ));
+    let top = std::fs::read_to_string(output.join("sum3/my-adder__0_Simple_top.v"))
+        .expect("Could not read top module.");
+    assert!(top.contains("  my_adder dut (\n"));
+
+    dir.delete();
+}
+
+#[test]
+fn batch_generates_embedded_circuits_of_input_dir_once() {
+    let dir = util::TempDir::create("batch_generates_embedded_circuits_of_input_dir_once");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "adder.dig",
+        "--include",
+        "sum3.dig",
+        "--hierarchy",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    assert!(!dir.file("adder__0_Simple.v").exists());
+    assert!(dir.file("sum3/adder__0_Simple.v").exists());
+
+    let manifest =
+        std::fs::read_to_string(dir.file("manifest.json")).expect("Could not read manifest.");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest["generated"].as_array().unwrap().len(), 3);
+
+    dir.delete();
+}
+
+#[test]
+fn vcd_dump_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--vcd",
+        "adder.vcd",
+        "--dump-scope",
+        "ports",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "initial begin\n  $dumpfile(\"adder.vcd\");\n  $dumpvars(1, tb);\nend\n",
+    ));
+}
+
+#[test]
+fn dump_after_failure_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--fst",
+        "adder.fst",
+        "--delay",
+        "10:5",
+        "--dump-after-failure",
+        "3",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "        if (error_count == 1) -> dump_trigger; \\\n",
+    ))
+    .stdout(predicates::str::contains(
+        "event dump_trigger;\ninitial begin\n  $dumpfile(\"adder.fst\");\n  $dumpvars;\n  @(dump_trigger);\n  #45;\n  $dumpoff;\nend\n",
+    ));
+}
+
+#[test]
+fn dump_after_failure_requires_dump_file() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--dump-after-failure",
+        "3",
+    ])
+    .assert()
+    .failure();
+}
+
+#[test]
+fn dump_scope_requires_dump_file() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--dump-scope",
+        "ports",
+    ])
+    .assert()
+    .failure();
+}
+
+#[test]
+fn fail_fast_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--fail-fast",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        r#"        error_count += 1; \
+        if (error_count >= 1) begin \
+            $display("Stopping at the first failed assertion"); \
+            $finish_and_return(1); \
+        end \
+    end
+"#,
+    ));
+}
+
+#[test]
+fn max_errors_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--max-errors",
+        "5",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        r#"        if (error_count >= 5) begin \
+            $display("Stopping after 5 failed assertions"); \"#,
+    ));
+}
+
+#[test]
+fn max_errors_must_be_positive() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--max-errors",
+        "0",
+    ])
+    .assert()
+    .failure();
+}
+
+#[test]
+fn sections_work() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--sections",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "integer section = -1;\ninteger section_checks [0:3];\ninteger section_errors [0:3];\n",
+    ))
+    .stdout(predicates::str::contains(
+        "if (section >= 0) section_checks[section] += 1;",
+    ))
+    .stdout(predicates::str::contains(
+        "    section = 3;\n    $display(\"Section hold\");\n",
+    ))
+    .stdout(predicates::str::contains(
+        "  $display(\"hold: %0d of %0d checks failed\", section_errors[3], section_checks[3]);\n",
+    ));
+}
+
+#[test]
+fn section_filter_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--section",
+        "count",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("`assert_eq(20, QD, "))
+    .stdout(predicates::str::contains("`assert_eq(6, ").not())
+    .stdout(predicates::str::contains("`assert_eq(25, ").not());
+}
+
+#[test]
+fn unknown_section_lists_available_sections() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--section",
+        "missing",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(
+        "The available sections are: load, reset, count, hold",
+    ));
+}
+
+#[test]
+fn row_filter_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--rows",
+        "1:",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("`assert_eq(").not());
+}
+
+#[test]
+fn line_filter_requires_range() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--lines",
+        "10",
+    ])
+    .assert()
+    .failure();
+}
+
+#[test]
+fn line_filter_rejects_empty_ranges() {
+    for (option, range, message) in [
+        (
+            "--rows",
+            "20:10",
+            "the range is empty, since 20 is greater than 10",
+        ),
+        (
+            "--lines",
+            "0:10",
+            "expected an integer of at least 1, found 0",
+        ),
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            "0",
+            option,
+            range,
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(message));
+    }
+}
+
+#[test]
+fn initial_inputs_work() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--init-inputs",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "initial begin\n    A = 0;\n    B = 0;\n\n    A = 1;\n    B = 1;\n",
+    ));
+}
+
+#[test]
+fn all_inputs_sets_unchanged_inputs() {
+    let count_clear = |extra_args: &[&str]| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        let output = cmd
+            .args([
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+                "--section",
+                "count",
+            ])
+            .args(extra_args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .matches("\\~CLR  = 1;")
+            .count()
+    };
+
+    assert_eq!(count_clear(&[]), 1);
+    assert_eq!(count_clear(&["--all-inputs"]), 48);
+    assert_eq!(count_clear(&["--checkpoint", "2"]), 24);
+}
+
+#[test]
+fn initial_inputs_use_circuit_defaults() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--init-inputs",
+        "default",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "initial begin\n    \\~LD  = 1;\n    \\~CLR  = 1;\n    CLK = 0;\n    ENT = 0;\n",
+    ));
+}
+
+#[test]
+fn reset_sequence_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--reset",
+        "A=0,B=0x3",
+        "--reset",
+        "B=Z",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "initial begin\n    A = 0;\n    B = 3;\n#10;\n\n    B = 'Z;\n#10;\n\n    A = 1;\n",
+    ));
+}
+
+#[test]
+fn reset_sequence_rejects_unknown_inputs() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--reset",
+        "S=0",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("\"S\""));
+}
+
+#[test]
+fn unlisted_inputs_are_set_to_defaults() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig")])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "Warning: The test does not set the inputs VCC, GND.",
+        ))
+        .stdout(predicates::str::contains(
+            "initial begin\n    VCC = 1;\n    GND = 0;\n\n",
+        ));
+}
+
+#[test]
+fn unlisted_inputs_can_be_left_undriven() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--no-input-defaults",
+    ])
+    .assert()
+    .success()
+    .stderr(predicates::str::contains("Warning").not())
+    .stdout(predicates::str::contains("VCC = ").not());
+}
+
+#[test]
+fn batch_sets_unlisted_inputs_to_defaults() {
+    let dir = util::TempDir::create("batch_sets_unlisted_inputs_to_defaults");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "74162.dig",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    let content = std::fs::read_to_string(dir.file("74162__0_unnamed.v"))
+        .expect("Could not read output file.");
+    assert!(content.contains("initial begin\n    VCC = 1;\n    GND = 0;\n\n"));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "batch",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        "--include",
+        "74162.dig",
+        "--no-input-defaults",
+        "-o",
+    ])
+    .arg(&dir.path)
+    .assert()
+    .success();
+
+    let content = std::fs::read_to_string(dir.file("74162__0_unnamed.v"))
+        .expect("Could not read output file.");
+    assert!(!content.contains("VCC = "));
+
+    dir.delete();
+}
+
+#[test]
+fn stub_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "stub",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+    ])
+    .assert()
+    .success()
+    .stdout(
+        r#"module adder (
+  input [7:0] A,
+  input [7:0] B,
+  output [7:0] \|S| ,
+  output C
+);
+  assign \|S|  = 'x;
+  assign C = 'x;
+endmodule
+"#,
+    );
+}
+
+#[test]
+fn stub_module_name_can_be_set() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "stub",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--module",
+        "counter",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::starts_with(
+        "module counter (\n  input \\~LD ,\n",
+    ));
+}
+
+#[test]
+fn digital_compat_matches_digital_export() {
+    let expected = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/74162_tb.v"
+    ))
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+        "--digital-compat",
+    ])
+    .assert()
+    .success()
+    .stdout(expected);
+}
+
+#[test]
+fn digital_compat_rejects_options_it_cannot_implement() {
+    for option in [
+        ["--reset", "~CLR=0"],
+        ["--init-inputs", "zero"],
+        ["--checkpoint", "4"],
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+            "--digital-compat",
+        ])
+        .args(option)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn sva_checker_is_bound_to_dut() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--sva",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("module tb_checker ("))
+    .stdout(predicates::str::contains(
+        "assert property (@(tb_sync::sample) check_2[row] |-> \\|S|  === expected_2[row])",
+    ))
+    .stdout(predicates::str::contains(
+        "bind adder tb_checker checker_inst (\n    .A(A),\n    .B(B),\n    .\\|S| (S),\n    .C(C)\n);",
+    ));
+}
+
+#[test]
+fn sva_rejects_zero_sampling_delay() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--sva",
+        "--delay",
+        "0:10",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(
+        "needs a delay between setting the inputs and checking the outputs",
+    ));
+}
+
+#[test]
+fn expected_vcd_is_written() {
+    let dir = util::TempDir::create("expected_vcd_is_written");
+    let path = dir.file("expected.vcd");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--expected-vcd",
+    ])
+    .arg(&path)
+    .assert()
+    .success();
+
+    let content = std::fs::read_to_string(&path).expect("Could not read expected VCD.");
+    assert_eq!(
+        content,
+        r#"$timescale 1s $end
+$scope module expected $end
+$var wire 8 ! A $end
+$var wire 8 " B $end
+$var wire 8 # |S| $end
+$var wire 1 $ C $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+bxxxxxxxx !
+bxxxxxxxx "
+bxxxxxxxx #
+x$
+$end
+b00000001 !
+b00000001 "
+#10
+b00000010 #
+"#
+    );
+
+    dir.delete();
+}
+
+#[test]
+fn import_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "import",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder_tb.v"),
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::starts_with(
+        "A B S C\n\n1 1 2 0\n255 1 0 1\n0 0 0 0\n",
+    ))
+    .stdout(predicates::str::ends_with("255 255 254 1\n"));
+}
+
+#[test]
+fn import_collapses_clock_pulses() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "import",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162_tb.v"),
+        "--clock",
+        "CLK",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::starts_with(
+        "CLK ~CLR ~LD ENT ENP D C B A QD QC QB QA\n\nC 1 0 0 0 0 0 0 0 0 0 0 0\nC 1 0 0 0 0 0 0 1 0 0 0 1\n",
+    ));
+}
+
+#[test]
+fn import_rejects_unknown_clock() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "import",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162_tb.v"),
+        "--clock",
+        "QA",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("There is no input called QA"));
+}
+
+#[test]
+fn from_vcd_samples_at_clock_edges() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "from-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/counter.vcd"),
+        "--inputs",
+        "top.D",
+        "--outputs",
+        "top.Q",
+        "--clock",
+        "top.CLK",
+    ])
+    .assert()
+    .success()
+    .stdout("CLK D Q\n\nC 5 5\nC 3 3\n");
+}
+
+#[test]
+fn from_vcd_drops_clock_from_inputs() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "from-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/counter.vcd"),
+        "--inputs",
+        "top.CLK,top.D",
+        "--outputs",
+        "top.Q",
+        "--clock",
+        "top.CLK",
+    ])
+    .assert()
+    .success()
+    .stdout("CLK D Q\n\nC 5 5\nC 3 3\n");
+}
+
+#[test]
+fn from_vcd_samples_at_intervals() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "from-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/counter.vcd"),
+        "--inputs",
+        "top.CLK,top.D",
+        "--outputs",
+        "top.Q",
+        "--interval",
+        "20",
+        "--offset",
+        "21",
+    ])
+    .assert()
+    .success()
+    .stdout("CLK D Q\n\nC 5 5\n");
+}
+
+#[test]
+fn from_vcd_lists_matching_signals() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "from-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/counter.vcd"),
+        "--inputs",
+        "D",
+        "--clock",
+        "top.CLK",
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("top.D, top.dut.D"));
+}
+
+#[test]
+fn check_vcd_passes() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "check-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.vcd"),
+    ])
+    .assert()
+    .success()
+    .stdout("All 3 checks passed.\n");
+}
+
+#[test]
+fn check_vcd_reports_failures() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "check-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "1",
+        "--vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.vcd"),
+        "--scope",
+        "top.tb",
+    ])
+    .assert()
+    .failure()
+    .stdout(predicates::str::contains(
+        "ASSERTION FAILED on line 2: |S| is 00000010, expected 00000011 at time 10",
+    ))
+    .stderr(predicates::str::contains("1 of 3 checks failed"));
+}
+
+#[test]
+fn check_vcd_reports_other_stimulus() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "check-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--vcd",
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/adder_other_stimulus.vcd"
+        ),
+    ])
+    .assert()
+    .failure()
+    .stdout(predicates::str::contains(
+        "ASSERTION FAILED on line 2: B is 00000010, expected 00000001 at time 10",
+    ))
+    .stderr(predicates::str::contains("2 of 3 checks failed"));
+}
+
+#[test]
+fn check_vcd_rejects_other_widths() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "check-vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+        "--vcd",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder_wide_sum.vcd"),
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(
+        "The signal |S| has 9 bits in the VCD file, but 8 bits in the test",
+    ));
+}
+
+#[test]
+fn formal_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "formal",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "0",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("module adder_formal;\n"))
+    .stdout(predicates::str::contains(
+        "  (* anyseq *) wire [7:0] A;\n  (* anyseq *) wire [7:0] B;\n",
+    ))
+    .stdout(predicates::str::contains(
+        "  adder dut (\n      A,\n      B,\n      \\|S| ,\n      C\n  );\n",
+    ))
+    .stdout(predicates::str::contains(
+        "      0: begin // line 2\n        assume(A == 8'b00000001);\n        assume(B == 8'b00000001);\n        assert(\\|S|  == 8'b00000010);\n      end\n",
+    ));
+}
+
+#[test]
+fn formal_writes_sby() {
+    let dir = util::TempDir::create("formal_writes_sby");
+
+    let file = dir.file("74181_formal.sv");
+    let sby = dir.file("74181.sby");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "formal",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74181.dig"),
+        "A plus B (A1001)",
+        "--mode",
+        "single-step",
+        "--source",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74181.v"),
+        "--sby",
+    ])
+    .arg(&sby)
+    .arg("-o")
+    .arg(&file)
+    .assert()
+    .success();
+
+    let content = std::fs::read_to_string(&sby).expect("Could not read sby file.");
+    assert!(content.starts_with("[options]\nmode bmc\ndepth 1\n\n[engines]\nsmtbmc\n"));
+    assert!(content.contains("read -formal 74181.v 74181_formal.sv\nprep -top 74181_formal\n"));
+
+    let content = std::fs::read_to_string(&file).expect("Could not read output file.");
+    assert!(content.contains("  \\74181  row511 (\n"));
+
+    dir.delete();
+}
+
+#[test]
+fn simulate_passes() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "simulate",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+    ])
+    .assert()
+    .success()
+    .stdout("All 1600 checks passed.\n");
+}
+
+#[test]
+fn simulate_reports_failures() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "simulate",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+        "1",
+    ])
+    .assert()
+    .failure()
+    .stdout("ASSERTION FAILED on line 2: |S| is 00000010, expected 00000011\n")
+    .stderr(predicates::str::contains("1 of 1 checks failed"));
+}
+
+#[test]
+fn simulate_rejects_unsupported_elements() {
+    let dir = util::TempDir::create("simulate_rejects_unsupported_elements");
+    let file = dir.file("74162.dig");
+    let circuit =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"))
+            .unwrap()
+            .replacen(
+                "<elementName>And</elementName>",
+                "<elementName>Counter</elementName>",
+                1,
+            );
+    std::fs::write(&file, circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Element Counter is not supported",
+        ));
+    dir.delete();
+}
+
+#[test]
+fn simulate_reports_missing_embedded_circuits() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "simulate",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74779.dig"),
+    ])
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains(
+        "Could not find the embedded circuit 74779-inc.dig",
+    ));
+}
+
+#[test]
+fn simulate_rejects_unsupported_attributes() {
+    let dir = util::TempDir::create("simulate_rejects_unsupported_attributes");
+    let file = dir.file("74162.dig");
+    let circuit = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/74162.dig"
+    ))
+    .unwrap()
+    .replacen(
+        "<elementName>And</elementName>\n      <elementAttributes/>",
+        "<elementName>And</elementName>\n      <elementAttributes>\n        <entry>\n          <string>wideShape</string>\n          <boolean>true</boolean>\n        </entry>\n      </elementAttributes>",
+        1,
+    );
+    std::fs::write(&file, circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "The attribute wideShape of And is not supported",
+        ));
+    dir.delete();
+}
+
+#[test]
+fn simulate_rejects_empty_splitter_widths() {
+    let dir = util::TempDir::create("simulate_rejects_empty_splitter_widths");
+    let file = dir.file("74162.dig");
+    let circuit =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"))
+            .unwrap()
+            .replacen("<string>1*4</string>", "<string>0*4</string>", 1);
+    std::fs::write(&file, circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Invalid splitting \"0*4\" of a splitter",
+        ));
+    dir.delete();
+}
+
+#[test]
+fn simulate_rejects_invalid_selector_bits() {
+    let dir = util::TempDir::create("simulate_rejects_invalid_selector_bits");
+    let file = dir.file("74162.dig");
+    let circuit = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/74162.dig"
+    ))
+    .unwrap()
+    .replacen(
+        "<elementName>Multiplexer</elementName>\n      <elementAttributes>",
+        "<elementName>Multiplexer</elementName>\n      <elementAttributes>\n        <entry>\n          <string>Selector Bits</string>\n          <int>64</int>\n        </entry>",
+        1,
+    );
+    std::fs::write(&file, circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Invalid number of selector bits 64",
+        ));
+    dir.delete();
+}
+
+#[test]
+fn recursive_embedding_is_rejected() {
+    let dir = util::TempDir::create("recursive_embedding_is_rejected");
+    let input = dir.file("input");
+    std::fs::create_dir(&input).unwrap();
+    let circuit =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sum3.dig"))
+            .unwrap()
+            .replace("adder.dig", "loop.dig");
+    std::fs::write(input.join("loop.dig"), circuit).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(input.join("loop.dig"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "The circuit loop.dig embeds itself",
+        ));
+
+    let output = dir.file("output");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("batch")
+        .arg(&input)
+        .args(["--hierarchy", "-o"])
+        .arg(&output)
+        .assert()
+        .success();
+    let manifest =
+        std::fs::read_to_string(output.join("manifest.json")).expect("Could not read manifest.");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(
+        manifest["skipped"][0]["reason"],
+        "The circuit loop.dig embeds itself"
+    );
+
+    dir.delete();
+}
+
+#[test]
+fn simulate_runs_embedded_circuits() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "simulate",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sum3.dig"),
+    ])
+    .assert()
+    .success()
+    .stdout("All 6 checks passed.\n");
+}
+
+#[test]
+fn simulate_runs_nested_embedded_circuits() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "simulate",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sum5.dig"),
+    ])
+    .assert()
+    .success()
+    .stdout("All 6 checks passed.\n");
+}
+
+#[test]
+fn simulate_searches_library() {
+    let dir = util::TempDir::create("simulate_searches_library");
+    let file = dir.file("sum3.dig");
+    std::fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sum3.dig"),
+        &file,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Could not find the embedded circuit adder.dig",
+        ));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("simulate")
+        .arg(&file)
+        .args([
+            "--library",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+        ])
+        .assert()
+        .success()
+        .stdout("All 6 checks passed.\n");
+
+    dir.delete();
+}
+
+#[test]
+fn netlist_works() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "netlist",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::starts_with(
+        "module adder (\n  input [7:0] A,\n  input [7:0] B,\n  output [7:0] \\|S| ,\n  output C\n);\n",
+    ))
+    .stdout(predicates::str::contains(
+        "  assign {s4, s3} = s0 + s1 + s2;\n",
+    ));
+}
+
+#[test]
+fn netlist_writes_embedded_circuits() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args([
+        "netlist",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/sum3.dig"),
+        "--module",
+        "top",
+    ])
+    .assert()
+    .success()
+    .stdout(predicates::str::starts_with("module adder (\n"))
+    .stdout(predicates::str::contains("endmodule\n\nmodule top (\n"))
+    .stdout(predicates::str::contains(
+        "  adder i4 (\n    .A(s3),\n    .B(s2),\n    .\\|S| (s5),\n    .C(s6)\n  );\n",
+    ));
+}
@@ -27,6 +27,7 @@ fn iverilog_command(
 #[test_with::executable(iverilog)]
 mod tests {
     use super::*;
+    use predicates::prelude::*;
     use rstest::rstest;
 
     #[test]
@@ -210,4 +211,227 @@ mod tests {
 
         dir.delete();
     }
+
+    #[test_with::executable(make)]
+    #[test]
+    fn batch_makefile_check_runs() {
+        let dir = util::TempDir::create("batch_makefile_check_runs");
+
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            "batch",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+            "--include",
+            "adder.dig",
+            "--test",
+            "Simple",
+            "--makefile",
+            "-o",
+        ])
+        .arg(&dir.path)
+        .assert()
+        .success();
+
+        let mut make = Command::new("make");
+        make.arg("-C").arg(&dir.path).arg("check");
+        make.assert()
+            .success()
+            .stdout(predicates::str::contains("PASS adder__0_Simple"));
+
+        dir.delete();
+    }
+
+    #[test]
+    fn batch_top_module_connects_digital_export() {
+        let dir = util::TempDir::create("batch_top_module_connects_digital_export");
+
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            "batch",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data"),
+            "--include",
+            "74162.dig",
+            "--makefile",
+            "-o",
+        ])
+        .arg(&dir.path)
+        .assert()
+        .success();
+
+        let exec_file = dir.file("out");
+
+        let testbench = dir.file("74162__0_unnamed.v");
+        let top = dir.file("74162__0_unnamed_top.v");
+        let mut iverilog = iverilog_command(&["74162.v"], &[&testbench, &top], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert().success().stdout("All tests passed.\n");
+
+        dir.delete();
+    }
+
+    #[test]
+    fn adder_failure_stops_with_fail_fast() {
+        let dir = util::TempDir::create("adder_failure_stops_with_fail_fast");
+
+        let file = dir.file("adder_failure_stops_with_fail_fast.v");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            "1",
+            "--fail-fast",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let exec_file = dir.file("out");
+
+        let mut iverilog = iverilog_command(&["adder.v", "adder_scaffold.v"], &[&file], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert()
+            .failure()
+            .stdout(predicates::str::contains(
+                "Stopping at the first failed assertion",
+            ))
+            .stdout(predicates::str::contains("There were failed assertions").not());
+
+        dir.delete();
+    }
+
+    #[test]
+    fn test_74162_reports_sections() {
+        let dir = util::TempDir::create("test_74162_reports_sections");
+
+        let file = dir.file("74162.v");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+            "--sections",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let exec_file = dir.file("out");
+
+        let mut iverilog = iverilog_command(&["74162.v", "74162_scaffold.v"], &[&file], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert()
+            .success()
+            .stdout(predicates::str::starts_with(
+                "Section load\nSection reset\n",
+            ))
+            .stdout(predicates::str::contains("hold: 0 of "))
+            .stdout(predicates::str::ends_with("All tests passed.\n"));
+
+        dir.delete();
+    }
+
+    #[test]
+    fn adder_stub_compiles_and_fails() {
+        let dir = util::TempDir::create("adder_stub_compiles_and_fails");
+
+        let stub = dir.file("adder_stub.v");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            "stub",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            "0",
+            "-o",
+        ])
+        .arg(&stub)
+        .assert()
+        .success();
+
+        let file = dir.file("adder_stub_test.v");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            "0",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let exec_file = dir.file("out");
+
+        let mut iverilog = iverilog_command(&["adder_scaffold.v"], &[&stub, &file], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert().failure();
+
+        dir.delete();
+    }
+
+    #[test]
+    fn test_74162_digital_compat_runs() {
+        let dir = util::TempDir::create("test_74162_digital_compat_runs");
+
+        let file = dir.file("74162_tb.v");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/74162.dig"),
+            "--digital-compat",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let exec_file = dir.file("out");
+
+        let mut iverilog = iverilog_command(&["74162.v"], &[&file], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert()
+            .success()
+            .stdout(predicates::str::ends_with("All tests passed.\n"));
+
+        dir.delete();
+    }
+
+    #[rstest]
+    #[case("adder", "0")]
+    #[case("74162", "0")]
+    #[case("74181", "A plus B (A1001)")]
+    fn netlist_replaces_digital_export(#[case] name: &str, #[case] test: &str) {
+        let dir = util::TempDir::create(format!("netlist_replaces_digital_export_{name}"));
+        let dig = format!("{}/tests/data/{name}.dig", env!("CARGO_MANIFEST_DIR"));
+
+        let netlist = dir.file(&format!("{name}.v"));
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args(["netlist", dig.as_str(), "-o"])
+            .arg(&netlist)
+            .assert()
+            .success();
+
+        let file = dir.file(&format!("{name}_test.v"));
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([dig.as_str(), test, "-o"])
+            .arg(&file)
+            .assert()
+            .success();
+
+        let exec_file = dir.file("out");
+
+        let scaffold = format!("{name}_scaffold.v");
+        let mut iverilog = iverilog_command(&[scaffold.as_str()], &[&netlist, &file], &exec_file);
+        iverilog.assert().success();
+
+        let mut cmd = Command::new(&exec_file);
+        cmd.assert().success().stdout("All tests passed.\n");
+
+        dir.delete();
+    }
 }
@@ -0,0 +1,75 @@
+use assert_cmd::Command;
+
+mod util;
+
+fn formal_command(dig: &str, test: &str, dir: &util::TempDir, args: &[&str]) -> Command {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("formal")
+        .arg(format!(
+            "{}/tests/data/{dig}.dig",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .arg(test)
+        .arg("--source")
+        .arg(format!("{}/tests/data/{dig}.v", env!("CARGO_MANIFEST_DIR")))
+        .arg("--sby")
+        .arg(dir.file(&format!("{dig}.sby")))
+        .arg("-o")
+        .arg(dir.file(&format!("{dig}_formal.sv")))
+        .args(args);
+    cmd
+}
+
+#[test_with::executable(sby)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_74181_single_step_passes() {
+        let dir = util::TempDir::create("test_74181_single_step_passes");
+
+        formal_command(
+            "74181",
+            "A plus B (A1001)",
+            &dir,
+            &["--mode", "single-step"],
+        )
+        .assert()
+        .success();
+
+        let mut sby = Command::new("sby");
+        sby.arg("-f").arg(dir.file("74181.sby"));
+        sby.assert().success();
+
+        dir.delete();
+    }
+
+    #[test]
+    fn adder_passes() {
+        let dir = util::TempDir::create("adder_passes");
+
+        formal_command("adder", "0", &dir, &[]).assert().success();
+
+        let mut sby = Command::new("sby");
+        sby.arg("-f").arg(dir.file("adder.sby"));
+        sby.assert().success();
+
+        dir.delete();
+    }
+
+    #[test]
+    fn adder_failure_fails() {
+        let dir = util::TempDir::create("adder_failure_fails");
+
+        formal_command("adder", "1", &dir, &[]).assert().success();
+
+        let mut sby = Command::new("sby");
+        sby.arg("-f").arg(dir.file("adder.sby"));
+        sby.assert()
+            .failure()
+            .stdout(predicates::str::contains("Assert failed in adder_formal"))
+            .stdout(predicates::str::contains("DONE (FAIL"));
+
+        dir.delete();
+    }
+}
@@ -0,0 +1,94 @@
+use assert_cmd::Command;
+
+mod util;
+
+#[test_with::executable(verilator)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sva_checker_binds_to_digital_export() {
+        let dir = util::TempDir::create("sva_checker_binds_to_digital_export");
+
+        let file = dir.file("adder_sva.sv");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            "0",
+            "--sva",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let mut verilator = Command::new("verilator");
+        verilator
+            .args([
+                "--lint-only",
+                "--timing",
+                "-sv",
+                "-Wno-fatal",
+                "--top-module",
+                "scaffold",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.v"),
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder_scaffold.v"),
+            ])
+            .arg(&file);
+        verilator.assert().success();
+
+        dir.delete();
+    }
+
+    /// Build the SVA bench of the given adder test case with Verilator, and run it
+    fn simulate_adder_sva(name: &str, test: &str) -> assert_cmd::assert::Assert {
+        let dir = util::TempDir::create(name);
+
+        let file = dir.file("adder_sva.sv");
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.args([
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.dig"),
+            test,
+            "--sva",
+            "-o",
+        ])
+        .arg(&file)
+        .assert()
+        .success();
+
+        let build = dir.file("obj_dir");
+        let mut verilator = Command::new("verilator");
+        verilator
+            .args([
+                "--binary",
+                "--timing",
+                "--assert",
+                "-sv",
+                "-Wno-fatal",
+                "--top-module",
+                "scaffold",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder.v"),
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/adder_scaffold.v"),
+            ])
+            .arg(&file)
+            .arg("--Mdir")
+            .arg(&build);
+        verilator.assert().success();
+
+        let assert = Command::new(build.join("Vscaffold")).assert();
+        dir.delete();
+        assert
+    }
+
+    #[test]
+    fn sva_bench_passes() {
+        simulate_adder_sva("sva_bench_passes", "0")
+            .success()
+            .stdout(predicates::str::contains("All tests passed."));
+    }
+
+    #[test]
+    fn sva_bench_fails() {
+        simulate_adder_sva("sva_bench_fails", "1").failure();
+    }
+}